@@ -13,7 +13,7 @@ use nom_supreme::{
 };
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
-use std::{iter::FromIterator, ops::Index};
+use std::{fmt, iter::FromIterator, ops::Index};
 use thiserror::Error;
 
 use crate::NomResult;
@@ -60,7 +60,7 @@ impl ExtractContext<&str, FastaError> for VerboseError<&str> {
 
 /// [`Fasta`] is a simple text-based format for genomic and proteomic sequences that stores an optional
 /// description and a sequence of [`RNA`](crate::genomics::nucleotide::RNA), [`DNA`](crate::genomics::nucleotide::DNA), or [`Amino Acids`](crate::proteomics::amino::AminoAcid).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Fasta<T>
 where
     T: Sequence,
@@ -100,10 +100,25 @@ where
     }
 }
 
+/// Writes the record as a `>` description line followed by the sequence.
+///
+/// Records without a description are written with a bare `>` line, which is read back as no description,
+/// so that they stay separate from the record before them
+impl<T> fmt::Display for Fasta<T>
+where
+    T: Sequence + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, ">{}", self.description.as_deref().unwrap_or_default())?;
+        write!(f, "{}", self.sequence)
+    }
+}
+
+/// Parses an optional description line, where an empty description is the same as none
 fn comment_line(src: &str) -> NomResult<'_, Option<Box<str>>> {
     delimited(one_of(">;"), not_line_ending, line_ending)
         .opt()
-        .map(|s| s.map(Into::into))
+        .map(|s: Option<&str>| s.filter(|s| !s.is_empty()).map(Into::into))
         .parse(src)
 }
 
@@ -162,7 +177,10 @@ where
 #[cfg(not(feature = "rayon"))]
 pub trait Sequence
 where
-    Self: Index<usize> + Extend<Self::Inner> + FromIterator<Self::Inner> + Sized,
+    Self: Index<usize, Output = Self::Inner>
+        + Extend<Self::Inner>
+        + FromIterator<Self::Inner>
+        + Sized,
 {
     const VALID_CHARS: &'static str;
 
@@ -174,6 +192,14 @@ where
 
     /// Serialize this `Sequence` to a raw binary stream
     fn serialize_bytes(&self) -> &[u8];
+
+    /// The number of members of this `Sequence`
+    fn len(&self) -> usize;
+
+    /// Returns `true` if this `Sequence` has no members
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// A sequence that can be:
@@ -183,7 +209,10 @@ where
 #[cfg(feature = "rayon")]
 pub trait Sequence
 where
-    Self: Index<usize> + Extend<Self::Inner> + FromIterator<Self::Inner> + Sized,
+    Self: Index<usize, Output = Self::Inner>
+        + Extend<Self::Inner>
+        + FromIterator<Self::Inner>
+        + Sized,
     Self: FromParallelIterator<Self::Inner>,
 {
     const VALID_CHARS: &'static str;
@@ -196,4 +225,12 @@ where
 
     /// Serialize this `Sequence` to a raw binary stream
     fn serialize_bytes(&self) -> &[u8];
+
+    /// The number of members of this `Sequence`
+    fn len(&self) -> usize;
+
+    /// Returns `true` if this `Sequence` has no members
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
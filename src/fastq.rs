@@ -1,26 +1,33 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, marker::PhantomData, ops::Range};
 
 use crate::fasta::Sequence;
+#[cfg(not(feature = "rayon"))]
 use crate::NomResult;
+use header::ReadHeader;
 use miette::{Diagnostic, NamedSource, SourceSpan};
 use nom::character::complete::multispace0;
 use nom::error::{VerboseError, VerboseErrorKind};
 use nom::multi::many1;
-use nom::sequence::{delimited, tuple};
+use nom::sequence::delimited;
 use nom::Parser;
 use nom_supreme::final_parser::{final_parser, ExtractContext};
 use nom_supreme::ParserExt;
-use quality::Quality;
+use quality::{Binning, Quality};
 pub use quality::{Phred, Solexa};
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 use thiserror::Error;
 use tracing::trace;
 
+pub mod demux;
+pub mod header;
+pub mod paired;
+pub mod qc;
 pub mod quality;
+pub mod stream;
+pub mod trim;
 
 pub type Descriptor = String;
-pub type QualitySequence<T, Q> = Vec<(T, Q)>;
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum FastQError {
@@ -28,6 +35,14 @@ pub enum FastQError {
     MismatchedDescription,
     #[error("File contained no FastQ data")]
     EmptyFile,
+    #[error("Paired reads have different names: {r1} and {r2}")]
+    MismatchedPair { r1: Box<str>, r2: Box<str> },
+    #[error("One file of a read pair ended before the other")]
+    UnpairedRead,
+    #[error("The barcodes of samples {a} and {b} are too similar to tell apart")]
+    BarcodeCollision { a: Box<str>, b: Box<str> },
+    #[error(transparent)]
+    IoErr(#[from] std::io::Error),
     #[error("{msg}")]
     ParsingError {
         msg: Box<str>,
@@ -65,13 +80,180 @@ impl ExtractContext<&str, FastQError> for VerboseError<&str> {
     }
 }
 
+/// A single FastQ entry: a description, a sequence, and the quality of each base.
+///
+/// Qualities are stored separately from the sequence as one byte per base, exactly as encoded in
+/// the file, so that they can be processed as a plain byte slice and written back unchanged.
+/// Typed [`Quality`] values are available through [`FastQRecord::quality`], [`FastQRecord::qualities`]
+/// and [`FastQRecord::pairs`].
+#[derive(Debug, Clone)]
+pub struct FastQRecord<S, Q>
+where
+    S: Sequence,
+    Q: Quality,
+{
+    pub description: Descriptor,
+    pub sequence: S,
+    /// The quality character of each base as its offset from `!`, see [`Quality::code`].
+    /// For [`Phred`] records this is the Phred score of each base
+    pub codes: Vec<u8>,
+    _quality: PhantomData<Q>,
+}
+
+impl<S, Q> FastQRecord<S, Q>
+where
+    S: Sequence,
+    Q: Quality,
+{
+    /// Creates a record from a sequence and the quality code of each of its bases
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `sequence` and `codes` are not the same length,
+    /// or if any code is past `~`
+    pub fn new(description: Descriptor, sequence: S, codes: Vec<u8>) -> Self {
+        assert_eq!(
+            sequence.len(),
+            codes.len(),
+            "A FastQ record must have exactly one quality score per base"
+        );
+        assert!(
+            codes.iter().all(|&code| code <= b'~' - b'!'),
+            "Quality codes must encode a character from '!' to '~'"
+        );
+        Self {
+            description,
+            sequence,
+            codes,
+            _quality: PhantomData,
+        }
+    }
+
+    /// Parses a string slice containing exactly one FastQ entry
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `src` is not a single, complete FastQ entry
+    pub fn parse(src: &str) -> Result<Self, FastQError> {
+        final_parser(
+            parsers::record::<S, Q>
+                .map(|(desc, seq_line, qual_line)| Self::from_lines(desc, &seq_line, &qual_line)),
+        )(src)
+    }
+
+    /// Builds a record from the lines of an entry that have already been checked by the parser
+    fn from_lines(desc: &str, seq_line: &str, qual_line: &str) -> Self {
+        Self::new(
+            desc.to_string(),
+            seq_line
+                .chars()
+                .map(|s| {
+                    S::Inner::try_from(s)
+                        .expect("Parser prevents us from reaching here with invalid characters")
+                })
+                .collect(),
+            qual_line
+                .chars()
+                .map(|q| {
+                    Q::try_from(q)
+                        .expect("Parser prevents us from reaching here with invalid characters")
+                        .code()
+                })
+                .collect(),
+        )
+    }
+
+    /// The number of bases in this record
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Returns `true` if this record has no bases
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The quality of the base at `index`, or `None` if it is out of bounds
+    #[must_use]
+    pub fn quality(&self, index: usize) -> Option<Q> {
+        self.codes.get(index).copied().map(Q::from_code)
+    }
+
+    /// Returns an iterator over the quality of each base
+    pub fn qualities(&self) -> impl Iterator<Item = Q> + '_ {
+        self.codes.iter().copied().map(Q::from_code)
+    }
+
+    /// Returns an iterator over each base along with its quality
+    pub fn pairs(&self) -> impl Iterator<Item = (S::Inner, Q)> + '_
+    where
+        S::Inner: Copy,
+    {
+        self.codes
+            .iter()
+            .enumerate()
+            .map(|(i, &code)| (self.sequence[i], Q::from_code(code)))
+    }
+
+    /// Copies the bases and qualities of `range` into a new record with the same description
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `range` is out of bounds
+    #[must_use]
+    pub fn slice(&self, range: Range<usize>) -> Self
+    where
+        S::Inner: Copy,
+    {
+        Self::new(
+            self.description.clone(),
+            range.clone().map(|i| self.sequence[i]).collect(),
+            self.codes[range].to_vec(),
+        )
+    }
+
+    /// Parses the description of this record into a structured [`ReadHeader`]
+    #[must_use]
+    pub fn header(&self) -> ReadHeader {
+        ReadHeader::parse(&self.description)
+    }
+
+    /// Bins every quality score of this record with `binning`, through its nearest Phred score
+    pub fn bin_qualities(&mut self, binning: Binning) {
+        for code in &mut self.codes {
+            *code = Q::from_phred(binning.bin(Q::from_code(*code).to_phred())).code();
+        }
+    }
+}
+
+impl<S, Q> fmt::Display for FastQRecord<S, Q>
+where
+    S: Sequence,
+    S::Inner: Copy + Into<char>,
+    Q: Quality,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "@{}", self.description)?;
+        let seq_line = (0..self.len())
+            .map(|i| self.sequence[i].into())
+            .collect::<String>();
+        writeln!(f, "{seq_line}")?;
+        writeln!(f, "+")?;
+        let qual_line = self.qualities().map(Into::<char>::into).collect::<String>();
+        writeln!(f, "{qual_line}")
+    }
+}
+
+/// Every entry of a FastQ file, by description
 #[derive(Debug)]
 pub struct FastQ<S, Q>
 where
     S: Sequence,
     Q: Quality,
 {
-    pub sequences: HashMap<Descriptor, QualitySequence<S::Inner, Q>>,
+    pub sequences: HashMap<Descriptor, FastQRecord<S, Q>>,
 }
 
 #[cfg(not(feature = "rayon"))]
@@ -82,123 +264,54 @@ where
 {
     #[tracing::instrument(skip_all)]
     pub fn parse(src: &str) -> Result<Self, FastQError> {
-        let sequences = final_parser(
+        let sequences = final_parser::<_, _, VerboseError<&str>, FastQError>(
             delimited(multispace0, many1(Self::parse_single), multispace0)
                 .context("FastQ files must contain at least one entry"),
         )(src)?
         .into_iter()
+        .map(|record| (record.description.clone(), record))
         .collect::<_>();
         Ok(Self { sequences })
     }
 
-    fn parse_single(src: &str) -> NomResult<'_, (Descriptor, QualitySequence<S::Inner, Q>)> {
-        tuple((
-            parsers::desc_line,
-            parsers::sequence_line::<S>,
-            parsers::optional_desc_line,
-            parsers::quality_line::<Q>,
-        ))
-        .context("Incomplete FastQ block")
-        .verify(|(desc, _, desc2, _)| {
-            if let Some(desc2) = desc2 {
-                desc2.is_empty() || desc == desc2
-            } else {
-                true
-            }
-        })
-        .context(
-            "FastQ entries must have matching descriptions if the second description is non-empty",
-        )
-        .map(|(desc, seq_line, _, qual_line)| {
-            (
-                desc.to_string(),
-                seq_line
-                    .chars()
-                    .zip(qual_line.chars())
-                    .map(|(s, q)| {
-                        (
-                            S::Inner::try_from(s).expect(
-                                "Parser prevents us from reaching here with invalid characters",
-                            ),
-                            Q::try_from(q).expect(
-                                "Parser prevents us from reaching here with invalid characters",
-                            ),
-                        )
-                    })
-                    .collect::<Vec<_>>(),
-            )
-        })
-        .parse(src)
+    fn parse_single(src: &str) -> NomResult<'_, FastQRecord<S, Q>> {
+        parsers::record::<S, Q>
+            .map(|(desc, seq_line, qual_line)| FastQRecord::from_lines(desc, &seq_line, &qual_line))
+            .parse(src)
     }
 }
 
 #[cfg(feature = "rayon")]
 impl<S, Q> FastQ<S, Q>
 where
-    S: Sequence,
+    S: Sequence + Send,
     Q: Quality + Send,
-    S::Inner: Send,
 {
     #[tracing::instrument(skip_all)]
     pub fn parse(src: &str) -> Result<Self, FastQError> {
-        let sequences = final_parser(
-            delimited(multispace0, many1(Self::parse_single), multispace0)
+        let sequences = final_parser::<_, _, VerboseError<&str>, FastQError>(
+            delimited(multispace0, many1(parsers::record::<S, Q>), multispace0)
                 .context("FastQ files must contain at least one entry"),
         )(src)?
         .into_par_iter()
+        .map(|(desc, seq_line, qual_line)| {
+            let record = FastQRecord::<S, Q>::from_lines(desc, &seq_line, &qual_line);
+            (record.description.clone(), record)
+        })
         .collect::<_>();
         Ok(Self { sequences })
     }
-
-    //#[tracing::instrument(skip_all)]
-    fn parse_single(src: &str) -> NomResult<'_, (Descriptor, QualitySequence<S::Inner, Q>)> {
-        tuple((
-            parsers::desc_line,
-            parsers::sequence_line::<S>,
-            parsers::optional_desc_line,
-            parsers::quality_line::<Q>,
-        ))
-        .context("Incomplete FastQ block")
-        .verify(|(desc, _, desc2, _)| {
-            if let Some(desc2) = desc2 {
-                desc2.is_empty() || desc == desc2
-            } else {
-                true
-            }
-        })
-        .context(
-            "FastQ entries must have matching descriptions if the second description is non-empty",
-        )
-        .map(|(desc, seq_line, _, qual_line)| {
-            (
-                desc.to_string(),
-                seq_line
-                    .as_bytes()
-                    .into_par_iter()
-                    .zip(qual_line.as_bytes().into_par_iter())
-                    .map(|(&s, &q)| {
-                        (
-                            S::Inner::try_from(char::from(s)).expect(
-                                "Parser prevents us from reaching here with invalid characters",
-                            ),
-                            Q::try_from(char::from(q)).expect(
-                                "Parser prevents us from reaching here with invalid characters",
-                            ),
-                        )
-                    })
-                    .collect::<Vec<_>>(),
-            )
-        })
-        .parse(src)
-    }
 }
 
 mod parsers {
+    use std::borrow::Cow;
+
     use nom::{
         bytes::complete::{is_a, take_while},
         character::complete::{line_ending, not_line_ending},
         combinator::opt,
-        sequence::delimited,
+        multi::many1,
+        sequence::{delimited, tuple},
         Parser,
     };
     use nom_supreme::{tag::complete::tag, ParserExt};
@@ -232,4 +345,63 @@ mod parsers {
             .context("Quality line contained invalid characters")
             .parse(src)
     }
+
+    /// Parses one or more wrapped sequence lines, up to the `+` separator line
+    pub fn sequence_block<T: Sequence>(src: &str) -> NomResult<'_, Cow<'_, str>> {
+        many1(sequence_line::<T>).map(join_lines).parse(src)
+    }
+
+    /// Parses wrapped quality lines until at least `len` quality characters have been read.
+    ///
+    /// The end of the block is found by counting quality characters rather than lines,
+    /// since a quality line may itself begin with `@`
+    pub fn quality_block<Q: Quality>(
+        len: usize,
+    ) -> impl FnMut(&str) -> NomResult<'_, Cow<'_, str>> {
+        move |mut src| {
+            let mut lines = Vec::new();
+            let mut read = 0;
+            loop {
+                let (rem, line) = quality_line::<Q>(src)?;
+                read += line.len();
+                lines.push(line);
+                src = rem;
+                if read >= len {
+                    return Ok((src, join_lines(lines)));
+                }
+            }
+        }
+    }
+
+    fn join_lines(lines: Vec<&str>) -> Cow<'_, str> {
+        match lines.as_slice() {
+            [line] => Cow::Borrowed(*line),
+            _ => Cow::Owned(lines.concat()),
+        }
+    }
+
+    /// Parses a full FastQ block into its description, sequence and quality, either of which may be wrapped
+    pub fn record<S: Sequence, Q: Quality>(
+        src: &str,
+    ) -> NomResult<'_, (&str, Cow<'_, str>, Cow<'_, str>)> {
+        let (rem, (desc, seq, _)) = tuple((desc_line, sequence_block::<S>, optional_desc_line))
+            .context("Incomplete FastQ block")
+            .verify(|(desc, _, desc2)| {
+                if let Some(desc2) = desc2 {
+                    desc2.is_empty() || desc == desc2
+                } else {
+                    true
+                }
+            })
+            .context(
+                "FastQ entries must have matching descriptions if the second description is non-empty",
+            )
+            .parse(src)?;
+        let len = seq.len();
+        let (rem, qual) = quality_block::<Q>(len)
+            .verify(|qual: &Cow<'_, str>| qual.len() == len)
+            .context("FastQ sequence and quality lines must be the same length")
+            .parse(rem)?;
+        Ok((rem, (desc, seq, qual)))
+    }
 }
@@ -0,0 +1,335 @@
+use std::io::{BufRead, Write};
+
+use super::{
+    paired::ReadPair,
+    quality::Quality,
+    stream::{FastQReader, FastQWriter},
+    FastQError, FastQRecord,
+};
+use crate::fasta::Sequence;
+
+/// A sample and the index sequence that identifies its reads
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sample {
+    pub name: Box<str>,
+    /// The index sequence, written as `i7+i5` for dual-indexed samples as in CASAVA headers
+    pub index: Box<str>,
+}
+
+/// The number of reads assigned to each sample by [`Demultiplexer::run`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DemuxStats {
+    /// Reads written for each sample, in the order of [`Demultiplexer::samples`]
+    pub per_sample: Vec<u64>,
+    /// Reads whose barcode matched no sample, or had no barcode at all
+    pub undetermined: u64,
+}
+
+/// Splits reads into samples by barcode, tolerating up to `max_mismatches` substitutions
+///
+/// The indices of a dual-indexed barcode are compared separately, so each of them may have up to
+/// `max_mismatches` substitutions
+#[derive(Debug, Clone)]
+pub struct Demultiplexer {
+    samples: Vec<Sample>,
+    max_mismatches: usize,
+}
+
+impl Demultiplexer {
+    /// Creates a demultiplexer for a sample sheet.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any two barcodes are within `2 * max_mismatches` substitutions
+    /// of each other, since a read could then match both samples
+    pub fn new(samples: Vec<Sample>, max_mismatches: usize) -> Result<Self, FastQError> {
+        for (i, a) in samples.iter().enumerate() {
+            for b in &samples[i + 1..] {
+                if distance(&a.index, &b.index).is_some_and(|d| d <= 2 * max_mismatches) {
+                    return Err(FastQError::BarcodeCollision {
+                        a: a.name.clone(),
+                        b: b.name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(Self {
+            samples,
+            max_mismatches,
+        })
+    }
+
+    /// The samples reads are assigned to
+    #[must_use]
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Returns the index of the sample whose barcode is within the mismatch tolerance of `barcode`
+    #[must_use]
+    pub fn assign(&self, barcode: &str) -> Option<usize> {
+        // Barcodes are at least 2 * max_mismatches + 1 apart, so at most one can be close enough
+        self.samples.iter().position(|sample| {
+            distance(&sample.index, barcode).is_some_and(|d| d <= self.max_mismatches)
+        })
+    }
+
+    /// Assigns a read using the index sequence in its header, see [`ReadHeader::index`](super::header::ReadHeader::index)
+    #[must_use]
+    pub fn assign_record<S: Sequence, Q: Quality>(
+        &self,
+        record: &FastQRecord<S, Q>,
+    ) -> Option<usize> {
+        self.assign(record.header().index()?)
+    }
+
+    /// Assigns a read using the sequence of its index read
+    #[must_use]
+    pub fn assign_index_read<S, Q>(&self, index_read: &FastQRecord<S, Q>) -> Option<usize>
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality,
+    {
+        let barcode = (0..index_read.len())
+            .map(|i| index_read.sequence[i].into())
+            .collect::<String>();
+        self.assign(&barcode)
+    }
+
+    /// Writes every read of `reads` to the output of the sample named by its header's index sequence,
+    /// or to `undetermined` if no sample matches
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading or writing fails, or if `reads` contains a malformed record
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not exactly one output per sample
+    pub fn run<R, W, S, Q>(
+        &self,
+        reads: FastQReader<R, S, Q>,
+        outputs: &mut [FastQWriter<W>],
+        undetermined: &mut FastQWriter<W>,
+    ) -> Result<DemuxStats, FastQError>
+    where
+        R: BufRead,
+        W: Write,
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        self.run_with(
+            reads.map(|read| read.map(|read| (self.assign_record(&read), read))),
+            outputs,
+            undetermined,
+        )
+    }
+
+    /// Writes every read of `reads` to the output of the sample matching the sequence of
+    /// the corresponding read of `index_reads`, or to `undetermined` if no sample matches
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading or writing fails, if either input contains a
+    /// malformed record, or if the reads and index reads do not pair up, either by count or by
+    /// [`read_name`](super::paired::read_name)
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there is not exactly one output per sample
+    pub fn run_with_index<R, I, W, S, Q>(
+        &self,
+        reads: FastQReader<R, S, Q>,
+        index_reads: FastQReader<I, S, Q>,
+        outputs: &mut [FastQWriter<W>],
+        undetermined: &mut FastQWriter<W>,
+    ) -> Result<DemuxStats, FastQError>
+    where
+        R: BufRead,
+        I: BufRead,
+        W: Write,
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        let mut index_reads = index_reads.fuse();
+        let mut reads = reads.fuse();
+        let assigned = std::iter::from_fn(|| match (reads.next(), index_reads.next()) {
+            (None, None) => None,
+            (Some(Ok(read)), Some(Ok(index))) => Some(
+                ReadPair::new(read, index).map(|pair| (self.assign_index_read(&pair.r2), pair.r1)),
+            ),
+            (Some(Err(e)), _) | (_, Some(Err(e))) => Some(Err(e)),
+            _ => Some(Err(FastQError::UnpairedRead)),
+        });
+        self.run_with(assigned, outputs, undetermined)
+    }
+
+    fn run_with<W, S, Q>(
+        &self,
+        assigned: impl Iterator<Item = Result<(Option<usize>, FastQRecord<S, Q>), FastQError>>,
+        outputs: &mut [FastQWriter<W>],
+        undetermined: &mut FastQWriter<W>,
+    ) -> Result<DemuxStats, FastQError>
+    where
+        W: Write,
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        assert_eq!(
+            outputs.len(),
+            self.samples.len(),
+            "There must be exactly one output per sample"
+        );
+        let mut stats = DemuxStats {
+            per_sample: vec![0; self.samples.len()],
+            undetermined: 0,
+        };
+        for result in assigned {
+            match result? {
+                (Some(sample), read) => {
+                    outputs[sample].write_record(&read)?;
+                    stats.per_sample[sample] += 1;
+                }
+                (None, read) => {
+                    undetermined.write_record(&read)?;
+                    stats.undetermined += 1;
+                }
+            }
+        }
+        Ok(stats)
+    }
+}
+
+/// The largest number of substitutions between corresponding indices of two barcodes,
+/// or `None` if they have different numbers of indices or any pair differs in length
+fn distance(a: &str, b: &str) -> Option<usize> {
+    if a.split('+').count() != b.split('+').count() {
+        return None;
+    }
+    a.split('+')
+        .zip(b.split('+'))
+        .map(|(a, b)| hamming(a, b))
+        .try_fold(0, |max, d| Some(max.max(d?)))
+}
+
+/// The number of positions at which two equal-length barcodes differ, or `None` if their lengths differ
+fn hamming(a: &str, b: &str) -> Option<usize> {
+    (a.len() == b.len()).then(|| {
+        a.bytes()
+            .zip(b.bytes())
+            .filter(|(a, b)| !a.eq_ignore_ascii_case(b))
+            .count()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Demultiplexer, Sample};
+    use crate::{
+        fastq::{
+            stream::{FastQReader, FastQWriter},
+            FastQError, Phred,
+        },
+        genomics::genome::DnaSeq,
+    };
+
+    fn samples(indices: &[&str]) -> Vec<Sample> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| Sample {
+                name: format!("sample{i}").into(),
+                index: index.into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collisions() {
+        assert!(Demultiplexer::new(samples(&["ACGTAC", "ACGTTT"]), 0).is_ok());
+        assert!(matches!(
+            Demultiplexer::new(samples(&["ACGTAC", "ACGTTT"]), 1),
+            Err(FastQError::BarcodeCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn dual_index() {
+        let demux = Demultiplexer::new(samples(&["ACGT+TTTT", "TGCA+GGGG"]), 1).unwrap();
+        // One substitution in each index
+        assert_eq!(demux.assign("ACGA+TTTA"), Some(0));
+        assert_eq!(demux.assign("AAAT+TTTT"), None);
+        assert_eq!(demux.assign("ACGT"), None);
+    }
+
+    #[test]
+    fn index_reads() {
+        const READS: &str = "@read1\nAAAA\n+\nIIII\n@read2\nCCCC\n+\nIIII\n";
+        const INDICES: &str = "@read1\nTTTTTA\n+\nIIIIII\n@read2\nGGGGGG\n+\nIIIIII\n";
+        let demux = Demultiplexer::new(samples(&["ACGTAA", "TTTTTT"]), 1).unwrap();
+        let mut outputs = vec![FastQWriter::new(Vec::new()), FastQWriter::new(Vec::new())];
+        let mut undetermined = FastQWriter::new(Vec::new());
+        let stats = demux
+            .run_with_index(
+                FastQReader::<_, DnaSeq, Phred>::new(READS.as_bytes()),
+                FastQReader::new(INDICES.as_bytes()),
+                &mut outputs,
+                &mut undetermined,
+            )
+            .unwrap();
+        assert_eq!(stats.per_sample, vec![0, 1]);
+        assert_eq!(stats.undetermined, 1);
+        let second = outputs.pop().unwrap().into_inner().unwrap();
+        assert!(String::from_utf8(second).unwrap().starts_with("@read1"));
+
+        let mut outputs = vec![FastQWriter::new(Vec::new()), FastQWriter::new(Vec::new())];
+        let mut undetermined = FastQWriter::new(Vec::new());
+        assert!(matches!(
+            demux.run_with_index(
+                FastQReader::<_, DnaSeq, Phred>::new(READS.as_bytes()),
+                FastQReader::new(&INDICES.as_bytes()[..INDICES.len() / 2]),
+                &mut outputs,
+                &mut undetermined,
+            ),
+            Err(FastQError::UnpairedRead)
+        ));
+
+        const SWAPPED: &str = "@read2\nGGGGGG\n+\nIIIIII\n@read1\nTTTTTA\n+\nIIIIII\n";
+        let mut outputs = vec![FastQWriter::new(Vec::new()), FastQWriter::new(Vec::new())];
+        let mut undetermined = FastQWriter::new(Vec::new());
+        assert!(matches!(
+            demux.run_with_index(
+                FastQReader::<_, DnaSeq, Phred>::new(READS.as_bytes()),
+                FastQReader::new(SWAPPED.as_bytes()),
+                &mut outputs,
+                &mut undetermined,
+            ),
+            Err(FastQError::MismatchedPair { .. })
+        ));
+    }
+
+    #[test]
+    fn split() {
+        const READS: &str = "@M1:1:FC:1:1:1:1 1:N:0:ACGTAC\nAAAA\n+\nIIII\n@M1:1:FC:1:1:1:2 1:N:0:TTGTTT\nCCCC\n+\nIIII\n@M1:1:FC:1:1:1:3 1:N:0:GGGGGG\nGGGG\n+\nIIII\n";
+        let demux = Demultiplexer::new(samples(&["ACGTAA", "TTTTTT"]), 1).unwrap();
+        let mut outputs = vec![FastQWriter::new(Vec::new()), FastQWriter::new(Vec::new())];
+        let mut undetermined = FastQWriter::new(Vec::new());
+        let stats = demux
+            .run(
+                FastQReader::<_, DnaSeq, Phred>::new(READS.as_bytes()),
+                &mut outputs,
+                &mut undetermined,
+            )
+            .unwrap();
+        assert_eq!(stats.per_sample, vec![1, 1]);
+        assert_eq!(stats.undetermined, 1);
+        let second = outputs.pop().unwrap().into_inner().unwrap();
+        assert!(String::from_utf8(second)
+            .unwrap()
+            .starts_with("@M1:1:FC:1:1:1:2"));
+    }
+}
@@ -0,0 +1,342 @@
+use std::{fmt, str::FromStr};
+
+/// A read header in the Illumina CASAVA 1.8+ format, e.g.
+/// `EAS139:136:FC706VJ:2:2104:15343:197393 1:Y:18:ATCACG`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CasavaHeader {
+    pub instrument: Box<str>,
+    pub run: u32,
+    pub flowcell: Box<str>,
+    pub lane: u8,
+    pub tile: u32,
+    pub x: u32,
+    pub y: u32,
+    /// `1` or `2` for the reads of a pair, or higher for index reads
+    pub read: u8,
+    /// `true` if the read was filtered out by the instrument (`Y`)
+    pub is_filtered: bool,
+    pub control: u16,
+    /// The index sequence, `i7+i5` for dual-indexed runs, or the sample number on some instruments
+    pub index: Box<str>,
+    /// Anything after the CASAVA fields, such as SAM-style tags or the `length=` that SRA adds
+    pub extra: Option<Box<str>>,
+}
+
+/// A read header from Illumina pipelines before CASAVA 1.8, e.g. `HWUSI-EAS100R:6:73:941:1973#0/1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LegacyHeader {
+    pub instrument: Box<str>,
+    pub lane: u8,
+    pub tile: u32,
+    pub x: u32,
+    pub y: u32,
+    /// The index sequence or multiplex number following `#`
+    pub index: Option<Box<str>>,
+    pub read: Option<u8>,
+}
+
+/// The parsed form of a FastQ description line
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReadHeader {
+    Casava(CasavaHeader),
+    Legacy(LegacyHeader),
+    /// A read named by the Sequence Read Archive, e.g. `SRR001666.1 071112_SLXA-EAS1_s_7:5:1:817:345 length=36`
+    Sra {
+        accession: Box<str>,
+        spot: u64,
+        read: Option<u8>,
+        /// Everything after the name, usually the original read name and length
+        comment: Option<Box<str>>,
+    },
+    /// A read name ending in `/1` or `/2` that is otherwise unrecognized
+    Paired {
+        name: Box<str>,
+        read: u8,
+    },
+    /// A description that matched none of the known formats
+    Raw(Box<str>),
+}
+
+impl ReadHeader {
+    /// Parses a FastQ description (without the leading `@`), falling back to [`ReadHeader::Raw`]
+    #[must_use]
+    pub fn parse(description: &str) -> Self {
+        let (name, comment) = match description.split_once(char::is_whitespace) {
+            Some((name, comment)) => (name, Some(comment.trim())),
+            None => (description, None),
+        };
+        let (name, read) = split_read_number(name);
+        comment
+            .and_then(|comment| CasavaHeader::parse(name, comment).map(Self::Casava))
+            .or_else(|| Self::parse_sra(name, read, comment))
+            .or_else(|| LegacyHeader::parse(name, read).map(Self::Legacy))
+            .or_else(|| {
+                read.map(|read| Self::Paired {
+                    name: name.into(),
+                    read,
+                })
+            })
+            .unwrap_or_else(|| Self::Raw(description.into()))
+    }
+
+    fn parse_sra(name: &str, read: Option<u8>, comment: Option<&str>) -> Option<Self> {
+        let (accession, spot) = name.split_once('.')?;
+        let is_run_accession = ["SRR", "ERR", "DRR"]
+            .iter()
+            .any(|prefix| accession.starts_with(prefix))
+            && accession[3..].bytes().all(|b| b.is_ascii_digit());
+        if !is_run_accession {
+            return None;
+        }
+        Some(Self::Sra {
+            accession: accession.into(),
+            spot: spot.parse().ok()?,
+            read,
+            comment: comment.filter(|c| !c.is_empty()).map(Into::into),
+        })
+    }
+
+    /// The read number within a pair, if the header records one
+    #[must_use]
+    pub fn read_number(&self) -> Option<u8> {
+        match self {
+            Self::Casava(header) => Some(header.read),
+            Self::Legacy(header) => header.read,
+            Self::Sra { read, .. } => *read,
+            Self::Paired { read, .. } => Some(*read),
+            Self::Raw(_) => None,
+        }
+    }
+
+    /// The flowcell lane, if the header records one
+    #[must_use]
+    pub fn lane(&self) -> Option<u8> {
+        match self {
+            Self::Casava(header) => Some(header.lane),
+            Self::Legacy(header) => Some(header.lane),
+            _ => None,
+        }
+    }
+
+    /// The flowcell tile, if the header records one
+    #[must_use]
+    pub fn tile(&self) -> Option<u32> {
+        match self {
+            Self::Casava(header) => Some(header.tile),
+            Self::Legacy(header) => Some(header.tile),
+            _ => None,
+        }
+    }
+
+    /// The index (barcode) sequence, if the header records one
+    #[must_use]
+    pub fn index(&self) -> Option<&str> {
+        match self {
+            Self::Casava(header) => Some(&header.index),
+            Self::Legacy(header) => header.index.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ReadHeader {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl fmt::Display for ReadHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Casava(h) => {
+                write!(
+                    f,
+                    "{}:{}:{}:{}:{}:{}:{} {}:{}:{}:{}",
+                    h.instrument,
+                    h.run,
+                    h.flowcell,
+                    h.lane,
+                    h.tile,
+                    h.x,
+                    h.y,
+                    h.read,
+                    if h.is_filtered { 'Y' } else { 'N' },
+                    h.control,
+                    h.index
+                )?;
+                if let Some(ref extra) = h.extra {
+                    write!(f, " {extra}")?;
+                }
+                Ok(())
+            }
+            Self::Legacy(h) => {
+                write!(f, "{}:{}:{}:{}:{}", h.instrument, h.lane, h.tile, h.x, h.y)?;
+                if let Some(ref index) = h.index {
+                    write!(f, "#{index}")?;
+                }
+                if let Some(read) = h.read {
+                    write!(f, "/{read}")?;
+                }
+                Ok(())
+            }
+            Self::Sra {
+                accession,
+                spot,
+                read,
+                comment,
+            } => {
+                write!(f, "{accession}.{spot}")?;
+                if let Some(read) = read {
+                    write!(f, "/{read}")?;
+                }
+                if let Some(comment) = comment {
+                    write!(f, " {comment}")?;
+                }
+                Ok(())
+            }
+            Self::Paired { name, read } => write!(f, "{name}/{read}"),
+            Self::Raw(raw) => f.write_str(raw),
+        }
+    }
+}
+
+impl CasavaHeader {
+    fn parse(name: &str, comment: &str) -> Option<Self> {
+        let mut fields = name.split(':');
+        let instrument = fields.next()?.into();
+        let run = fields.next()?.parse().ok()?;
+        let flowcell = fields.next()?.into();
+        let lane = fields.next()?.parse().ok()?;
+        let tile = fields.next()?.parse().ok()?;
+        let x = fields.next()?.parse().ok()?;
+        let y = fields.next()?.parse().ok()?;
+        if fields.next().is_some() {
+            return None;
+        }
+        let (comment, extra) = match comment.split_once(char::is_whitespace) {
+            Some((comment, extra)) => (comment, Some(extra)),
+            None => (comment, None),
+        };
+        let mut fields = comment.split(':');
+        let read = fields.next()?.parse().ok()?;
+        let is_filtered = match fields.next()? {
+            "Y" => true,
+            "N" => false,
+            _ => return None,
+        };
+        let control = fields.next()?.parse().ok()?;
+        let index = fields.next().unwrap_or_default().into();
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            instrument,
+            run,
+            flowcell,
+            lane,
+            tile,
+            x,
+            y,
+            read,
+            is_filtered,
+            control,
+            index,
+            extra: extra.filter(|extra| !extra.is_empty()).map(Into::into),
+        })
+    }
+}
+
+impl LegacyHeader {
+    fn parse(name: &str, read: Option<u8>) -> Option<Self> {
+        let (name, index) = match name.split_once('#') {
+            Some((name, index)) => (name, Some(index.into())),
+            None => (name, None),
+        };
+        let mut fields = name.split(':');
+        let instrument = fields.next()?.into();
+        let lane = fields.next()?.parse().ok()?;
+        let tile = fields.next()?.parse().ok()?;
+        let x = fields.next()?.parse().ok()?;
+        let y = fields.next()?.parse().ok()?;
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            instrument,
+            lane,
+            tile,
+            x,
+            y,
+            index,
+            read,
+        })
+    }
+}
+
+/// Splits a trailing `/1` style read number from a read name
+fn split_read_number(name: &str) -> (&str, Option<u8>) {
+    match name.rsplit_once('/') {
+        Some((rest, read)) if !rest.is_empty() => match read.parse() {
+            Ok(read) => (rest, Some(read)),
+            Err(_) => (name, None),
+        },
+        _ => (name, None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LegacyHeader, ReadHeader};
+
+    #[test]
+    fn casava() {
+        let src = "EAS139:136:FC706VJ:2:2104:15343:197393 1:Y:18:ATCACG";
+        let header = ReadHeader::parse(src);
+        let ReadHeader::Casava(ref casava) = header else {
+            panic!("Expected a CASAVA header, got {header:?}");
+        };
+        assert_eq!(&*casava.flowcell, "FC706VJ");
+        assert_eq!((casava.lane, casava.tile, casava.y), (2, 2104, 197393));
+        assert!(casava.is_filtered);
+        assert_eq!(header.index(), Some("ATCACG"));
+        assert_eq!(header.to_string(), src);
+
+        for src in [
+            "EAS139:136:FC706VJ:2:2104:15343:197393 1:N:0:ACGT BC:Z:xyz",
+            "EAS139:136:FC706VJ:2:2104:15343:197393 1:N:0:ACGT length=151",
+        ] {
+            let header = ReadHeader::parse(src);
+            assert_eq!(header.index(), Some("ACGT"));
+            assert_eq!(header.to_string(), src);
+        }
+    }
+
+    #[test]
+    fn legacy() {
+        assert_eq!(
+            ReadHeader::parse("HWUSI-EAS100R:6:73:941:1973#0/1"),
+            ReadHeader::Legacy(LegacyHeader {
+                instrument: "HWUSI-EAS100R".into(),
+                lane: 6,
+                tile: 73,
+                x: 941,
+                y: 1973,
+                index: Some("0".into()),
+                read: Some(1),
+            })
+        );
+    }
+
+    #[test]
+    fn sra_and_fallback() {
+        let header = ReadHeader::parse("SRR001666.1 071112_SLXA-EAS1_s_7:5:1:817:345 length=36");
+        assert!(matches!(header, ReadHeader::Sra { spot: 1, .. }));
+        assert_eq!(ReadHeader::parse("read7/2").read_number(), Some(2));
+        assert_eq!(
+            ReadHeader::parse("some read"),
+            ReadHeader::Raw("some read".into())
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    fmt,
+    io::{BufRead, Write},
+};
+
+use super::{
+    quality::Quality,
+    stream::{FastQReader, FastQWriter},
+    FastQError, FastQRecord,
+};
+use crate::fasta::Sequence;
+
+/// The two reads sequenced from either end of the same fragment
+pub struct ReadPair<S, Q>
+where
+    S: Sequence,
+    Q: Quality,
+{
+    pub r1: FastQRecord<S, Q>,
+    pub r2: FastQRecord<S, Q>,
+}
+
+impl<S, Q> ReadPair<S, Q>
+where
+    S: Sequence,
+    Q: Quality,
+{
+    /// Pairs two reads, checking that they share a [`read_name`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the reads have different names
+    pub fn new(r1: FastQRecord<S, Q>, r2: FastQRecord<S, Q>) -> Result<Self, FastQError> {
+        if read_name(&r1.description) == read_name(&r2.description) {
+            Ok(Self { r1, r2 })
+        } else {
+            Err(FastQError::MismatchedPair {
+                r1: r1.description.into(),
+                r2: r2.description.into(),
+            })
+        }
+    }
+}
+
+impl<S, Q> fmt::Debug for ReadPair<S, Q>
+where
+    S: Sequence + fmt::Debug,
+    S::Inner: fmt::Debug,
+    Q: Quality + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadPair")
+            .field("r1", &self.r1)
+            .field("r2", &self.r2)
+            .finish()
+    }
+}
+
+impl<S, Q> Clone for ReadPair<S, Q>
+where
+    S: Sequence + Clone,
+    S::Inner: Clone,
+    Q: Quality + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            r1: self.r1.clone(),
+            r2: self.r2.clone(),
+        }
+    }
+}
+
+/// Returns the part of a read description that is shared by both reads of a pair.
+///
+/// This is the description up to the first whitespace, which drops the Illumina comment fields
+/// (such as `1:N:0:ATCACG`), with any trailing `/1` or `/2` removed.
+#[must_use]
+pub fn read_name(description: &str) -> &str {
+    let name = description
+        .split_once(char::is_whitespace)
+        .map_or(description, |(name, _)| name);
+    name.strip_suffix("/1")
+        .or_else(|| name.strip_suffix("/2"))
+        .unwrap_or(name)
+}
+
+fn pair<S: Sequence, Q: Quality>(
+    r1: Option<Result<FastQRecord<S, Q>, FastQError>>,
+    r2: Option<Result<FastQRecord<S, Q>, FastQError>>,
+) -> Option<Result<ReadPair<S, Q>, FastQError>> {
+    match (r1, r2) {
+        (None, None) => None,
+        (Some(Err(e)), _) | (_, Some(Err(e))) => Some(Err(e)),
+        (Some(Ok(r1)), Some(Ok(r2))) => Some(ReadPair::new(r1, r2)),
+        _ => Some(Err(FastQError::UnpairedRead)),
+    }
+}
+
+/// Reads [`ReadPair`]s from two files, one holding the first read of each pair and the other the second
+#[derive(Debug)]
+pub struct PairedReader<R1, R2, S, Q> {
+    r1: FastQReader<R1, S, Q>,
+    r2: FastQReader<R2, S, Q>,
+}
+
+impl<R1, R2, S, Q> PairedReader<R1, R2, S, Q>
+where
+    R1: BufRead,
+    R2: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    pub fn new(r1: R1, r2: R2) -> Self {
+        Self {
+            r1: FastQReader::new(r1),
+            r2: FastQReader::new(r2),
+        }
+    }
+}
+
+impl<R1, R2, S, Q> Iterator for PairedReader<R1, R2, S, Q>
+where
+    R1: BufRead,
+    R2: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    type Item = Result<ReadPair<S, Q>, FastQError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        pair(self.r1.next(), self.r2.next())
+    }
+}
+
+/// Reads [`ReadPair`]s from a single interleaved file, where each first read is directly followed by its mate
+#[derive(Debug)]
+pub struct InterleavedReader<R, S, Q> {
+    inner: FastQReader<R, S, Q>,
+}
+
+impl<R, S, Q> InterleavedReader<R, S, Q>
+where
+    R: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    pub fn new(src: R) -> Self {
+        Self {
+            inner: FastQReader::new(src),
+        }
+    }
+}
+
+impl<R, S, Q> Iterator for InterleavedReader<R, S, Q>
+where
+    R: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    type Item = Result<ReadPair<S, Q>, FastQError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let r1 = self.inner.next();
+        let r2 = r1.as_ref().and_then(|_| self.inner.next());
+        pair(r1, r2)
+    }
+}
+
+impl<W: Write> FastQWriter<W> {
+    /// Writes both reads of a pair one after the other, producing an interleaved file
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to the destination fails
+    pub fn write_pair<S, Q>(&mut self, pair: &ReadPair<S, Q>) -> Result<(), FastQError>
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        self.write_record(&pair.r1)?;
+        self.write_record(&pair.r2)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_name, InterleavedReader, PairedReader};
+    use crate::{
+        fastq::{stream::FastQWriter, FastQError, Phred},
+        genomics::genome::DnaSeq,
+    };
+
+    const R1: &str = "@read1/1\nACGT\n+\nIIII\n\n@read2 1:N:0:ATCACG\nGGCC\n+\n####\n";
+    const R2: &str = "@read1/2\nTTTT\n+\nIIII\n@read2 2:N:0:ATCACG\nAAAA\n+\n####\n";
+
+    #[test]
+    fn names() {
+        assert_eq!(read_name("SRR001.1/2"), "SRR001.1");
+        assert_eq!(
+            read_name("M00123:4:000000000-A1B2C:1:1101:15589:1331 1:N:0:1"),
+            "M00123:4:000000000-A1B2C:1:1101:15589:1331"
+        );
+    }
+
+    #[test]
+    fn paired_round_trip() {
+        let pairs = PairedReader::<_, _, DnaSeq, Phred>::new(R1.as_bytes(), R2.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(pairs.len(), 2);
+
+        let mut writer = FastQWriter::new(Vec::new());
+        for pair in &pairs {
+            writer.write_pair(pair).unwrap();
+        }
+        let interleaved = writer.into_inner().unwrap();
+        let reread = InterleavedReader::<_, DnaSeq, Phred>::new(interleaved.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(reread.len(), 2);
+        assert_eq!(reread[1].r2.description, "read2 2:N:0:ATCACG");
+    }
+
+    #[test]
+    fn mismatched() {
+        let mut reader = PairedReader::<_, _, DnaSeq, Phred>::new(
+            R1.as_bytes(),
+            R2.split_once("IIII\n").unwrap().1.as_bytes(),
+        );
+        assert!(matches!(
+            reader.next(),
+            Some(Err(FastQError::MismatchedPair { .. }))
+        ));
+        assert!(matches!(reader.next(), Some(Err(FastQError::UnpairedRead))));
+    }
+}
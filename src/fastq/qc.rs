@@ -0,0 +1,393 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{quality::Quality, FastQ, FastQRecord};
+use crate::fasta::Sequence;
+
+/// The highest Phred score that can be encoded in a FastQ quality line
+pub const MAX_PHRED: usize = 93;
+
+/// The number of distinct sequences [`QcReport::overrepresented`] keeps track of, as in FastQC.
+/// Reads first seen after this many are only counted if they match a sequence already tracked
+pub const TRACKED_SEQUENCES: usize = 100_000;
+
+/// The number of bases of each read kept for [`QcReport::overrepresented`], as in FastQC
+pub const TRACKED_LENGTH: usize = 50;
+
+/// The bases tracked by [`PositionStats::bases`], with `T` and `U` sharing a bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum BaseClass {
+    A,
+    C,
+    G,
+    T,
+    N,
+    /// Any other IUPAC ambiguity code or a gap
+    Other,
+}
+
+impl BaseClass {
+    fn of(base: char) -> Self {
+        match base {
+            'A' => Self::A,
+            'C' => Self::C,
+            'G' => Self::G,
+            'T' | 'U' => Self::T,
+            'N' => Self::N,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A histogram of Phred scores, indexed by score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QualityHistogram(pub [u64; MAX_PHRED + 1]);
+
+impl Default for QualityHistogram {
+    fn default() -> Self {
+        Self([0; MAX_PHRED + 1])
+    }
+}
+
+impl QualityHistogram {
+    /// The number of scores counted
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.0.iter().sum()
+    }
+
+    /// The mean of every score counted, or `None` if the histogram is empty
+    #[must_use]
+    pub fn mean(&self) -> Option<f64> {
+        let count = self.count();
+        (count > 0).then(|| {
+            self.0
+                .iter()
+                .enumerate()
+                .map(|(score, &n)| score as f64 * n as f64)
+                .sum::<f64>()
+                / count as f64
+        })
+    }
+
+    /// The lowest score `s` such that at least `fraction` of the counted scores are `<= s`
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `fraction` is not in the range `0.0..=1.0`
+    #[must_use]
+    pub fn quantile(&self, fraction: f64) -> Option<u8> {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "fraction must be between 0 and 1, got {fraction}"
+        );
+        let target = (self.count() as f64 * fraction).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (score, &n) in self.0.iter().enumerate() {
+            seen += n;
+            if seen >= target {
+                return Some(score as u8);
+            }
+        }
+        None
+    }
+
+    /// The first quartile, median and third quartile of the counted scores
+    #[must_use]
+    pub fn quartiles(&self) -> Option<(u8, u8, u8)> {
+        Some((
+            self.quantile(0.25)?,
+            self.quantile(0.5)?,
+            self.quantile(0.75)?,
+        ))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+    }
+}
+
+/// Base and quality counts at a single read position across every read
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PositionStats {
+    /// The distribution of quality scores at this position
+    pub quality: QualityHistogram,
+    /// The number of reads with each [`BaseClass`] at this position
+    pub bases: [u64; 6],
+}
+
+impl PositionStats {
+    /// The number of reads with the given class of base at this position
+    #[must_use]
+    pub fn base_count(&self, base: BaseClass) -> u64 {
+        self.bases[base as usize]
+    }
+
+    /// The fraction of reads covering this position with a G or C at this position
+    #[must_use]
+    pub fn gc_fraction(&self) -> f64 {
+        let total = self.bases.iter().sum::<u64>();
+        if total == 0 {
+            0.0
+        } else {
+            (self.base_count(BaseClass::G) + self.base_count(BaseClass::C)) as f64 / total as f64
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.quality.merge(&other.quality);
+        for (a, b) in self.bases.iter_mut().zip(other.bases) {
+            *a += b;
+        }
+    }
+}
+
+/// A quality control summary of a set of reads, in the spirit of [FastQC](https://www.bioinformatics.babraham.ac.uk/projects/fastqc/)
+///
+/// Reports are built by adding reads one at a time with [`QcReport::add_read`] and can be combined with
+/// [`QcReport::merge`], so that [`QcReport::from_fastq`] can compute a report in a single parallel pass.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QcReport {
+    /// The number of reads
+    pub reads: u64,
+    /// The total number of bases across every read
+    pub bases: u64,
+    /// The number of `N` bases across every read
+    pub n_count: u64,
+    /// Statistics for each read position, starting at the first base
+    pub per_position: Vec<PositionStats>,
+    /// The number of reads for each mean read quality, rounded to the nearest whole score
+    pub mean_quality: QualityHistogram,
+    /// The number of reads for each GC percentage, rounded to the nearest whole percent
+    pub gc_content: Vec<u64>,
+    /// The number of reads of each length
+    pub lengths: BTreeMap<usize, u64>,
+    /// The number of reads starting with each of the first [`TRACKED_SEQUENCES`] distinct sequences seen
+    sequences: HashMap<Box<str>, u64>,
+    /// The number of reads that did not fit in `sequences`
+    untracked: u64,
+}
+
+impl QcReport {
+    /// Computes a report over every read of a [`FastQ`] file
+    #[cfg(not(feature = "rayon"))]
+    #[must_use]
+    pub fn from_fastq<S, Q>(fastq: &FastQ<S, Q>) -> Self
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality,
+    {
+        fastq
+            .sequences
+            .values()
+            .fold(Self::default(), |mut report, record| {
+                report.add_record(record);
+                report
+            })
+    }
+
+    /// Computes a report over every read of a [`FastQ`] file
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn from_fastq<S, Q>(fastq: &FastQ<S, Q>) -> Self
+    where
+        S: Sequence,
+        S: Sync,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Sync,
+    {
+        fastq
+            .sequences
+            .par_iter()
+            .fold(Self::default, |mut report, (_, record)| {
+                report.add_record(record);
+                report
+            })
+            .reduce(Self::default, Self::merge)
+    }
+
+    /// Adds a single read to this report, from its bases and the Phred score of each of them
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `sequence` and `phred` are not the same length
+    pub fn add_read<S>(&mut self, sequence: &S, phred: &[u8])
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+    {
+        assert_eq!(
+            sequence.len(),
+            phred.len(),
+            "A read must have exactly one quality score per base"
+        );
+        let len = phred.len();
+        if self.per_position.len() < len {
+            self.per_position.resize_with(len, Default::default);
+        }
+        if self.gc_content.is_empty() {
+            self.gc_content = vec![0; 101];
+        }
+        let mut read = String::with_capacity(len.min(TRACKED_LENGTH));
+        let (mut quality_sum, mut gc) = (0_u64, 0_usize);
+        for (i, (&score, position)) in phred.iter().zip(self.per_position.iter_mut()).enumerate() {
+            let base: char = sequence[i].into();
+            let score = score.min(MAX_PHRED as u8);
+            let class = BaseClass::of(base);
+            position.quality.0[usize::from(score)] += 1;
+            position.bases[class as usize] += 1;
+            quality_sum += u64::from(score);
+            match class {
+                BaseClass::G | BaseClass::C => gc += 1,
+                BaseClass::N => self.n_count += 1,
+                _ => {}
+            }
+            if i < TRACKED_LENGTH {
+                read.push(base);
+            }
+        }
+        self.reads += 1;
+        self.bases += len as u64;
+        *self.lengths.entry(len).or_default() += 1;
+        if len > 0 {
+            let mean = (quality_sum as f64 / len as f64).round() as usize;
+            self.mean_quality.0[mean] += 1;
+            self.gc_content[(gc as f64 * 100.0 / len as f64).round() as usize] += 1;
+        }
+        self.track(read.into_boxed_str(), 1);
+    }
+
+    /// Counts `reads` starting with `sequence`, if it is already tracked or there is room for it
+    fn track(&mut self, sequence: Box<str>, reads: u64) {
+        let room = self.sequences.len() < TRACKED_SEQUENCES;
+        match self.sequences.get_mut(&sequence) {
+            Some(count) => *count += reads,
+            None if room => {
+                self.sequences.insert(sequence, reads);
+            }
+            None => self.untracked += reads,
+        }
+    }
+
+    /// Adds a single [`FastQRecord`] to this report
+    pub fn add_record<S, Q>(&mut self, record: &FastQRecord<S, Q>)
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality,
+    {
+        let phred = record.qualities().map(Q::to_phred).collect::<Vec<_>>();
+        self.add_read(&record.sequence, &phred);
+    }
+
+    /// Combines the reads counted by two reports
+    #[must_use]
+    pub fn merge(mut self, other: Self) -> Self {
+        self.reads += other.reads;
+        self.bases += other.bases;
+        self.n_count += other.n_count;
+        if self.per_position.len() < other.per_position.len() {
+            self.per_position
+                .resize_with(other.per_position.len(), Default::default);
+        }
+        for (a, b) in self.per_position.iter_mut().zip(&other.per_position) {
+            a.merge(b);
+        }
+        self.mean_quality.merge(&other.mean_quality);
+        if self.gc_content.is_empty() {
+            self.gc_content = other.gc_content;
+        } else {
+            for (a, b) in self.gc_content.iter_mut().zip(other.gc_content) {
+                *a += b;
+            }
+        }
+        for (len, count) in other.lengths {
+            *self.lengths.entry(len).or_default() += count;
+        }
+        self.untracked += other.untracked;
+        for (seq, count) in other.sequences {
+            self.track(seq, count);
+        }
+        self
+    }
+
+    /// The mean read length, or `None` if no reads have been counted
+    #[must_use]
+    pub fn mean_length(&self) -> Option<f64> {
+        (self.reads > 0).then(|| self.bases as f64 / self.reads as f64)
+    }
+
+    /// Every distinct read sequence making up more than `fraction` of all reads, most frequent first.
+    ///
+    /// Like FastQC, only the first [`TRACKED_LENGTH`] bases of each read are compared, and only the first
+    /// [`TRACKED_SEQUENCES`] distinct sequences are counted, which bounds the memory a report needs.
+    /// FastQC reports sequences above `0.001` (0.1%) as overrepresented.
+    #[must_use]
+    pub fn overrepresented(&self, fraction: f64) -> Vec<(&str, u64)> {
+        let threshold = self.reads as f64 * fraction;
+        let mut found = self
+            .sequences
+            .iter()
+            .filter(|(_, &count)| count as f64 > threshold)
+            .map(|(seq, &count)| (seq.as_ref(), count))
+            .collect::<Vec<_>>();
+        found.sort_unstable_by(|(a_seq, a), (b_seq, b)| b.cmp(a).then_with(|| a_seq.cmp(b_seq)));
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BaseClass, QcReport, TRACKED_LENGTH, TRACKED_SEQUENCES};
+    use crate::tests::read;
+
+    #[test]
+    fn report() {
+        let mut report = QcReport::default();
+        report.add_record(&read("ACGN", "IIII"));
+        let mut other = QcReport::default();
+        other.add_record(&read("ACG", "+++"));
+        other.add_record(&read("ACG", "+++"));
+        let report = report.merge(other);
+
+        assert_eq!(report.reads, 3);
+        assert_eq!(report.bases, 10);
+        assert_eq!(report.n_count, 1);
+        assert_eq!(report.lengths.get(&3), Some(&2));
+        assert_eq!(report.per_position[0].base_count(BaseClass::A), 3);
+        assert_eq!(
+            report.per_position[3].quality.quartiles(),
+            Some((40, 40, 40))
+        );
+        assert_eq!(report.per_position[0].quality.quantile(0.5), Some(10));
+        assert_eq!(report.mean_quality.0[10], 2);
+        assert_eq!(report.gc_content[67], 2);
+        assert_eq!(report.overrepresented(0.5), vec![("ACG", 2)]);
+    }
+
+    #[test]
+    fn bounded_sequences() {
+        let mut report = QcReport::default();
+        let long = "A".repeat(TRACKED_LENGTH + 10);
+        report.add_record(&read(&long, &"I".repeat(long.len())));
+        assert_eq!(
+            report.overrepresented(0.0),
+            vec![(&long[..TRACKED_LENGTH], 1)]
+        );
+
+        for i in 1..TRACKED_SEQUENCES {
+            report.track(i.to_string().into(), 1);
+        }
+        report.track("new".into(), 2);
+        report.track("A".repeat(TRACKED_LENGTH).into(), 1);
+        assert_eq!(report.sequences.len(), TRACKED_SEQUENCES);
+        assert_eq!(report.untracked, 2);
+        assert_eq!(report.sequences[&long[..TRACKED_LENGTH]], 2);
+    }
+}
@@ -3,6 +3,90 @@ pub trait Quality: From<f64> + Into<f64> + TryFrom<char, Error = String> + Into<
     fn is_valid(c: char) -> bool {
         ('!'..='~').contains(&c)
     }
+
+    /// Decodes a quality score from the offset of its character from `!`
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `code` is above 93, the offset of `~`
+    fn from_code(code: u8) -> Self {
+        assert!(code <= 93, "Quality code {code} is past '~'");
+        Self::try_from(char::from(code + 0x21)).expect("Every character from '!' to '~' is valid")
+    }
+
+    /// The offset of this quality score's character from `!`
+    fn code(self) -> u8 {
+        Into::<char>::into(self) as u8 - 0x21
+    }
+
+    /// Creates a quality score from a whole Phred score
+    fn from_phred(score: u8) -> Self {
+        Self::from(10.0_f64.powf(f64::from(score) / -10.0))
+    }
+
+    /// Converts this quality score to the nearest whole Phred score
+    fn to_phred(self) -> u8 {
+        phred_of(self).round().clamp(0.0, f64::from(u8::MAX)) as u8
+    }
+
+    /// Combines the qualities of two reads that called the same base at an overlapping position.
+    ///
+    /// Both reads would have to be wrong, and wrong in the same way, for the base to be wrong,
+    /// so the combined quality is higher than either
+    fn merge_agreeing(self, other: Self) -> Self {
+        let (p1, p2): (f64, f64) = (self.into(), other.into());
+        Self::from((p1 * p2 / 3.0) / (1.0 - p1 - p2 + 4.0 * p1 * p2 / 3.0))
+    }
+
+    /// Combines the qualities of two reads that called different bases at an overlapping position,
+    /// giving the quality of the base called by the higher quality read
+    fn merge_disagreeing(self, other: Self) -> Self {
+        let (p1, p2): (f64, f64) = (self.into(), other.into());
+        let (best, worst) = if p1 <= p2 { (p1, p2) } else { (p2, p1) };
+        Self::from(best * (1.0 - worst / 3.0) / (best + worst - 4.0 * best * worst / 3.0))
+    }
+}
+
+/// Converts a quality score to a (fractional) Phred score through its error probability
+pub(crate) fn phred_of<Q: Quality>(quality: Q) -> f64 {
+    let prob: f64 = quality.into();
+    -10.0 * prob.log10()
+}
+
+/// A scheme for reducing the number of distinct quality scores, which makes quality lines compress far better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binning {
+    /// The 8 level binning used by Illumina HiSeq and MiSeq instruments: 6, 15, 22, 27, 33, 37 and 40,
+    /// with no-calls (`Q0` and `Q1`) left as they are
+    Illumina8,
+    /// The 4 level binning used by Illumina NovaSeq instruments: 2, 12, 23 and 37,
+    /// with no-calls (`Q0` to `Q2`) left as they are
+    NovaSeq4,
+}
+
+impl Binning {
+    /// Returns the Phred score that `score` is binned to
+    #[must_use]
+    pub fn bin(self, score: u8) -> u8 {
+        match self {
+            Self::Illumina8 => match score {
+                0..=1 => score,
+                2..=9 => 6,
+                10..=19 => 15,
+                20..=24 => 22,
+                25..=29 => 27,
+                30..=34 => 33,
+                35..=39 => 37,
+                _ => 40,
+            },
+            Self::NovaSeq4 => match score {
+                0..=2 => score,
+                3..=14 => 12,
+                15..=30 => 23,
+                _ => 37,
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -14,10 +98,71 @@ fn contains() {
     }
 }
 
+#[cfg(test)]
+#[test]
+fn conversions() {
+    for score in 0..=Phred::MAX.score() {
+        let phred = Phred::from_phred(score);
+        assert_eq!(Phred::from(phred.error_probability()), phred);
+    }
+    for score in Solexa::MIN..=Solexa::MAX {
+        let solexa = Solexa(score);
+        assert_eq!(Solexa::from(solexa.error_probability()), solexa);
+        assert_eq!(Solexa::from_code(solexa.code()), solexa);
+    }
+    assert_eq!(Phred::from(0.001), Phred::from_phred(30));
+    assert_eq!(
+        Solexa::try_from('h').map(Phred::from),
+        Ok(Phred::from_phred(40))
+    );
+    assert_eq!(Solexa::try_from(';').map(Solexa::score), Ok(-5));
+    assert_eq!(Phred::from(Solexa::from(Phred::from_phred(20))).score(), 20);
+    assert!(Phred::from_phred(30) > Phred::from_phred(20));
+
+    let q30 = Phred::from_phred(30);
+    assert_eq!(q30.merge_agreeing(q30).score(), 65);
+    assert_eq!(q30.merge_disagreeing(Phred::from_phred(20)).score(), 10);
+    assert_eq!(q30.binned(Binning::Illumina8).score(), 33);
+    assert_eq!(q30.binned(Binning::NovaSeq4).score(), 23);
+}
+
 /// A Phred quality score, encoded by the formula `-10 * log10(P)` for a probability `P`
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Phred(u8);
 
+impl Phred {
+    /// The highest score that can be encoded, as `~`
+    pub const MAX: Self = Self(93);
+
+    /// Creates a Phred score, or returns `None` if it is too high to be encoded
+    #[must_use]
+    pub const fn new(score: u8) -> Option<Self> {
+        if score <= Self::MAX.0 {
+            Some(Self(score))
+        } else {
+            None
+        }
+    }
+
+    /// The whole Phred score
+    #[must_use]
+    pub const fn score(self) -> u8 {
+        self.0
+    }
+
+    /// The probability that the base this score belongs to was called incorrectly
+    #[must_use]
+    pub fn error_probability(self) -> f64 {
+        self.into()
+    }
+
+    /// Returns the score this score is binned to by `binning`
+    #[must_use]
+    pub fn binned(self, binning: Binning) -> Self {
+        Self(binning.bin(self.0))
+    }
+}
+
 impl From<Phred> for f64 {
     fn from(code: Phred) -> Self {
         10.0_f64.powf(code.0 as f64 / -10.0)
@@ -25,8 +170,19 @@ impl From<Phred> for f64 {
 }
 
 impl From<f64> for Phred {
-    fn from(score: f64) -> Self {
-        Self((f64::log10(score) * -10.0) as u8)
+    /// Converts an error probability to the nearest Phred score, saturating at [`Phred::MAX`]
+    fn from(prob: f64) -> Self {
+        Self(
+            (f64::log10(prob) * -10.0)
+                .round()
+                .clamp(0.0, Self::MAX.0 as f64) as u8,
+        )
+    }
+}
+
+impl From<Solexa> for Phred {
+    fn from(score: Solexa) -> Self {
+        Self::from(f64::from(score))
     }
 }
 
@@ -51,11 +207,47 @@ impl From<Phred> for char {
     }
 }
 
-impl Quality for Phred {}
+impl Quality for Phred {
+    fn from_code(code: u8) -> Self {
+        Self::new(code).expect("Quality code is past '~'")
+    }
+
+    fn code(self) -> u8 {
+        self.0
+    }
+
+    fn from_phred(score: u8) -> Self {
+        Self(score)
+    }
+
+    fn to_phred(self) -> u8 {
+        self.0
+    }
+}
 
 /// A Quality score from pre-1.3 versions of the Solexa pipeline, encoded by the formula `-10 * log10(p / 1-p)`
-#[derive(Debug)]
-pub struct Solexa(u8);
+///
+/// Solexa scores are written with an offset of 64 and can be negative, usually no lower than -5 (`;`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Solexa(i8);
+
+impl Solexa {
+    const OFFSET: i8 = 64;
+    const MIN: i8 = 0x21 - Self::OFFSET;
+    const MAX: i8 = 0x7e - Self::OFFSET;
+
+    /// The whole Solexa score
+    #[must_use]
+    pub const fn score(self) -> i8 {
+        self.0
+    }
+
+    /// The probability that the base this score belongs to was called incorrectly
+    #[must_use]
+    pub fn error_probability(self) -> f64 {
+        self.into()
+    }
+}
 
 impl From<Solexa> for f64 {
     fn from(code: Solexa) -> Self {
@@ -66,8 +258,16 @@ impl From<Solexa> for f64 {
 }
 
 impl From<f64> for Solexa {
+    /// Converts an error probability to the nearest Solexa score that can be encoded
     fn from(prob: f64) -> Self {
-        Solexa((-10.0_f64 * f64::log10(prob / (1.0 - prob))) as u8)
+        let score = -10.0_f64 * f64::log10(prob / (1.0 - prob));
+        Solexa(score.round().clamp(Self::MIN as f64, Self::MAX as f64) as i8)
+    }
+}
+
+impl From<Phred> for Solexa {
+    fn from(score: Phred) -> Self {
+        Self::from(f64::from(score))
     }
 }
 
@@ -81,14 +281,14 @@ impl TryFrom<char> for Solexa {
                 r#"Solexa quality character must be between '!' (0x21) and '~' (0x7e), got {value} ({byte:x})"#
             ))
         } else {
-            Ok(Self(byte))
+            Ok(Self(byte as i8 - Self::OFFSET))
         }
     }
 }
 
 impl From<Solexa> for char {
     fn from(score: Solexa) -> Self {
-        score.0 as char
+        (score.0 + Solexa::OFFSET) as u8 as char
     }
 }
 
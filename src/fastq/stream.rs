@@ -0,0 +1,183 @@
+use std::{
+    io::{BufRead, Write},
+    marker::PhantomData,
+};
+
+use super::{quality::Quality, FastQError, FastQRecord};
+use crate::fasta::Sequence;
+
+/// Reads [`FastQRecord`]s one at a time from a [`BufRead`], without holding the whole file in memory
+#[derive(Debug)]
+pub struct FastQReader<R, S, Q> {
+    src: R,
+    buf: String,
+    _types: PhantomData<fn() -> (S, Q)>,
+}
+
+impl<R, S, Q> FastQReader<R, S, Q>
+where
+    R: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    pub fn new(src: R) -> Self {
+        Self {
+            src,
+            buf: String::new(),
+            _types: PhantomData,
+        }
+    }
+
+    /// Reads the next record, or returns `None` at the end of the input
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from the source fails or the next record is malformed
+    pub fn read_record(&mut self) -> Result<Option<FastQRecord<S, Q>>, FastQError> {
+        self.buf.clear();
+        // Skip any blank lines between records
+        loop {
+            if self.src.read_line(&mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            if !self.buf.trim().is_empty() {
+                break;
+            }
+            self.buf.clear();
+        }
+        // Sequence lines run until the `+` separator, and quality lines until there is one quality
+        // character per base, since a wrapped quality line may itself start with `@`
+        let mut seq_len = 0;
+        loop {
+            let start = self.buf.len();
+            if self.src.read_line(&mut self.buf)? == 0 {
+                return FastQRecord::parse(&self.buf).map(Some);
+            }
+            let line = &self.buf[start..];
+            if line.starts_with('+') {
+                break;
+            }
+            seq_len += line.trim_end().len();
+        }
+        let mut qual_len = 0;
+        loop {
+            let start = self.buf.len();
+            if self.src.read_line(&mut self.buf)? == 0 {
+                break;
+            }
+            qual_len += self.buf[start..].trim_end().len();
+            if qual_len >= seq_len {
+                break;
+            }
+        }
+        if !self.buf.ends_with('\n') {
+            self.buf.push('\n');
+        }
+        FastQRecord::parse(&self.buf).map(Some)
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+}
+
+impl<R, S, Q> Iterator for FastQReader<R, S, Q>
+where
+    R: BufRead,
+    S: Sequence,
+    Q: Quality,
+{
+    type Item = Result<FastQRecord<S, Q>, FastQError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Writes [`FastQRecord`]s in the four line FastQ format
+#[derive(Debug)]
+pub struct FastQWriter<W> {
+    dst: W,
+}
+
+impl<W: Write> FastQWriter<W> {
+    pub fn new(dst: W) -> Self {
+        Self { dst }
+    }
+
+    /// Writes a single record
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to the destination fails
+    pub fn write_record<S, Q>(&mut self, record: &FastQRecord<S, Q>) -> Result<(), FastQError>
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        write!(self.dst, "{record}")?;
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if flushing the destination fails
+    pub fn into_inner(mut self) -> Result<W, FastQError> {
+        self.dst.flush()?;
+        Ok(self.dst)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FastQReader, FastQWriter};
+    use crate::{
+        fastq::{quality::Quality, FastQ, Phred, Solexa},
+        genomics::genome::DnaSeq,
+    };
+
+    // The second quality line of the first record starts with '@'
+    const WRAPPED: &str = "@read1\nACGTAC\nGTAC\n+\nIIIIII\n@III\n@read2\nAAAA\n+read2\nII\nII\n";
+
+    #[test]
+    fn wrapped_reader() {
+        let records = FastQReader::<_, DnaSeq, Phred>::new(WRAPPED.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].len(), 10);
+        assert_eq!(records[1].description, "read2");
+        assert_eq!(records[1].codes, vec![40; 4]);
+        assert_eq!(records[1].quality(0), Some(Phred::from_phred(40)));
+        assert_eq!(records[1].to_string(), "@read2\nAAAA\n+\nIIII\n");
+    }
+
+    #[test]
+    fn solexa_round_trip() {
+        let qual = (-5_i8..=40)
+            .map(|score| char::from((score + 64) as u8))
+            .collect::<String>();
+        let src = format!("@read\n{}\n+\n{qual}\n", "A".repeat(qual.len()));
+        let mut writer = FastQWriter::new(Vec::new());
+        for record in FastQReader::<_, DnaSeq, Solexa>::new(src.as_bytes()) {
+            let record = record.unwrap();
+            assert_eq!(record.quality(0).map(Solexa::score), Some(-5));
+            writer.write_record(&record).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(writer.into_inner().unwrap()).unwrap(),
+            src
+        );
+    }
+
+    #[test]
+    fn wrapped_parse() {
+        let fastq = FastQ::<DnaSeq, Phred>::parse(WRAPPED).unwrap();
+        assert_eq!(fastq.sequences.len(), 2);
+        assert_eq!(fastq.sequences["read1"].len(), 10);
+    }
+}
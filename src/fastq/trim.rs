@@ -0,0 +1,318 @@
+use std::ops::{Add, AddAssign, Range};
+
+use super::{quality::Quality, FastQRecord};
+use crate::fasta::Sequence;
+
+/// A single read preprocessing operation, applied by a [`Trimmer`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrimStep {
+    /// Removes the first occurrence of an adapter and everything after it, including an adapter that
+    /// runs off the 3' end of the read by at least `min_overlap` bases
+    Adapter {
+        sequence: Box<str>,
+        /// The largest fraction of the overlapping bases that may differ from the adapter
+        max_mismatch_rate: f64,
+        min_overlap: usize,
+    },
+    /// Removes bases from the 5' end while their error probability is above that of the Phred threshold
+    Leading(u8),
+    /// Removes bases from the 3' end while their error probability is above that of the Phred threshold
+    Trailing(u8),
+    /// Scans from the 5' end and cuts the read where the mean quality of `size` consecutive bases, as
+    /// `-10 * log10(P)` of each error probability `P`, first drops below `quality`, as done by
+    /// Trimmomatic's `SLIDINGWINDOW`
+    SlidingWindow { size: usize, quality: u8 },
+    /// Trims the 3' end with Mott's algorithm, keeping the part of the read that maximizes the sum of
+    /// `limit - P` over its bases, where `P` is each error probability and `limit` is that of the Phred threshold
+    Mott(u8),
+    /// Removes `N` bases from both ends of the read
+    TrimNs,
+}
+
+/// The number of bases each kind of [`TrimStep`] removed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrimStats {
+    /// The number of reads these statistics cover
+    pub reads: usize,
+    /// The number of reads in which an adapter was found
+    pub adapters_found: usize,
+    /// Bases removed by [`TrimStep::Adapter`]
+    pub adapter: usize,
+    /// Bases removed from the 5' end by quality trimming
+    pub quality_5p: usize,
+    /// Bases removed from the 3' end by quality trimming
+    pub quality_3p: usize,
+    /// Bases removed by [`TrimStep::TrimNs`]
+    pub n: usize,
+    /// The total length of the reads before trimming
+    pub bases_in: usize,
+    /// The total length of the reads after trimming
+    pub bases_out: usize,
+}
+
+impl Add for TrimStats {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for TrimStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.reads += rhs.reads;
+        self.adapters_found += rhs.adapters_found;
+        self.adapter += rhs.adapter;
+        self.quality_5p += rhs.quality_5p;
+        self.quality_3p += rhs.quality_3p;
+        self.n += rhs.n;
+        self.bases_in += rhs.bases_in;
+        self.bases_out += rhs.bases_out;
+    }
+}
+
+/// Applies an ordered list of [`TrimStep`]s to reads
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trimmer {
+    pub steps: Vec<TrimStep>,
+}
+
+impl Trimmer {
+    /// Creates a trimmer that applies `steps` in order
+    #[must_use]
+    pub fn new(steps: Vec<TrimStep>) -> Self {
+        Self { steps }
+    }
+
+    /// Finds the part of a read that survives every step, without copying it,
+    /// from its bases and the quality of each of them
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `sequence` and `qualities` are not the same length
+    pub fn trim_range<S, Q>(&self, sequence: &S, qualities: &[Q]) -> (Range<usize>, TrimStats)
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        assert_eq!(
+            sequence.len(),
+            qualities.len(),
+            "A read must have exactly one quality score per base"
+        );
+        let probabilities = qualities.iter().map(|&q| q.into()).collect::<Vec<f64>>();
+        let mut range = 0..probabilities.len();
+        let mut stats = TrimStats {
+            reads: 1,
+            bases_in: probabilities.len(),
+            ..TrimStats::default()
+        };
+        for step in &self.steps {
+            let before = range.clone();
+            let scores = &probabilities[range.clone()];
+            let base = |i: usize| -> char { sequence[before.start + i].into() };
+            let kept = match *step {
+                TrimStep::Adapter {
+                    ref sequence,
+                    max_mismatch_rate,
+                    min_overlap,
+                } => {
+                    let found =
+                        find_adapter(scores.len(), base, sequence, max_mismatch_rate, min_overlap);
+                    if found.is_some() {
+                        stats.adapters_found += 1;
+                    }
+                    0..found.unwrap_or(scores.len())
+                }
+                TrimStep::Leading(threshold) => {
+                    let limit = error_probability(threshold);
+                    let start = scores
+                        .iter()
+                        .position(|&p| p <= limit)
+                        .unwrap_or(scores.len());
+                    start..scores.len()
+                }
+                TrimStep::Trailing(threshold) => {
+                    let limit = error_probability(threshold);
+                    let end = scores
+                        .iter()
+                        .rposition(|&p| p <= limit)
+                        .map_or(0, |i| i + 1);
+                    0..end
+                }
+                TrimStep::SlidingWindow { size, quality } => {
+                    0..sliding_window(scores, size, f64::from(quality))
+                }
+                TrimStep::Mott(threshold) => 0..mott(scores, error_probability(threshold)),
+                TrimStep::TrimNs => {
+                    let start = (0..scores.len())
+                        .position(|i| base(i) != 'N')
+                        .unwrap_or(scores.len());
+                    let end = (0..scores.len())
+                        .rposition(|i| base(i) != 'N')
+                        .map_or(start, |i| i + 1);
+                    start..end
+                }
+            };
+            range = before.start + kept.start..before.start + kept.end;
+            let (removed_5p, removed_3p) = (range.start - before.start, before.end - range.end);
+            match step {
+                TrimStep::Adapter { .. } => stats.adapter += removed_3p,
+                TrimStep::TrimNs => stats.n += removed_5p + removed_3p,
+                _ => {
+                    stats.quality_5p += removed_5p;
+                    stats.quality_3p += removed_3p;
+                }
+            }
+        }
+        stats.bases_out = range.len();
+        (range, stats)
+    }
+
+    /// Trims a copy of a [`FastQRecord`], returning the trimmed record and the bases each step removed
+    pub fn trim_record<S, Q>(&self, record: &FastQRecord<S, Q>) -> (FastQRecord<S, Q>, TrimStats)
+    where
+        S: Sequence,
+        S::Inner: Copy + Into<char>,
+        Q: Quality + Copy,
+    {
+        let qualities = record.qualities().collect::<Vec<_>>();
+        let (range, stats) = self.trim_range(&record.sequence, &qualities);
+        (record.slice(range), stats)
+    }
+}
+
+/// Returns the index of the leftmost match of `adapter` in the `len` bases given by `base` with at most
+/// `max_mismatch_rate` mismatches, where a match may run off the end if at least `min_overlap` bases overlap
+fn find_adapter(
+    len: usize,
+    base: impl Fn(usize) -> char,
+    adapter: &str,
+    max_mismatch_rate: f64,
+    min_overlap: usize,
+) -> Option<usize> {
+    let adapter = adapter.as_bytes();
+    (0..len).find(|&start| {
+        let overlap = adapter.len().min(len - start);
+        if overlap == 0 || overlap < min_overlap.min(adapter.len()) {
+            return false;
+        }
+        let allowed = (max_mismatch_rate * overlap as f64).floor() as usize;
+        (start..start + overlap)
+            .zip(adapter)
+            .filter(|&(i, &a)| base(i) != char::from(a))
+            .nth(allowed)
+            .is_none()
+    })
+}
+
+/// The error probability of a whole Phred score
+fn error_probability(phred: u8) -> f64 {
+    10.0_f64.powf(f64::from(phred) / -10.0)
+}
+
+/// Returns the length to keep after sliding-window trimming of bases with the error probabilities `probabilities`
+fn sliding_window(probabilities: &[f64], size: usize, threshold: f64) -> usize {
+    if size == 0 || probabilities.len() < size {
+        return probabilities.len();
+    }
+    let scores = probabilities
+        .iter()
+        .map(|p| -10.0 * p.log10())
+        .collect::<Vec<_>>();
+    let mut sum = scores[..size].iter().sum::<f64>();
+    for start in 0..=scores.len() - size {
+        if start > 0 {
+            sum += scores[start + size - 1] - scores[start - 1];
+        }
+        if sum / (size as f64) < threshold {
+            // Keep any leading bases of the failing window that still pass on their own
+            return start
+                + scores[start..start + size]
+                    .iter()
+                    .take_while(|&&s| s >= threshold)
+                    .count();
+        }
+    }
+    scores.len()
+}
+
+/// Returns the length to keep after Mott trimming of bases with the error probabilities `probabilities`.
+///
+/// The bases cut from the 3' end are those that add up to the most `p - limit`,
+/// so the bases kept add up to the most `limit - p`
+fn mott(probabilities: &[f64], limit: f64) -> usize {
+    let (mut sum, mut max, mut end) = (0.0, 0.0, probabilities.len());
+    for (i, &p) in probabilities.iter().enumerate().rev() {
+        sum += p - limit;
+        if sum > max {
+            max = sum;
+            end = i;
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TrimStep, Trimmer};
+    use crate::{
+        fastq::{FastQRecord, Solexa},
+        genomics::genome::DnaSeq,
+        tests::read,
+    };
+
+    #[test]
+    fn quality() {
+        let read = read("NACGTACGTN", "#IIIII5###");
+        let qualities = read.qualities().collect::<Vec<_>>();
+        let trim_range = |steps| Trimmer::new(steps).trim_range(&read.sequence, &qualities);
+        let (range, stats) = trim_range(vec![TrimStep::Leading(3), TrimStep::Trailing(3)]);
+        assert_eq!(range, 1..7);
+        assert_eq!((stats.quality_5p, stats.quality_3p), (1, 3));
+        let (range, _) = trim_range(vec![TrimStep::Mott(20)]);
+        // A single Q2 base outweighs the five Q40 bases after it
+        assert_eq!(range, 0..0);
+        let (range, _) = trim_range(vec![TrimStep::SlidingWindow {
+            size: 2,
+            quality: 20,
+        }]);
+        assert_eq!(range, 0..7);
+    }
+
+    #[test]
+    fn mott() {
+        let read = read("ACGTACGTAC", "IIIII5#I##");
+        let (trimmed, stats) = Trimmer::new(vec![TrimStep::Mott(20)]).trim_record(&read);
+        assert_eq!(trimmed.to_string(), "@read\nACGTAC\n+\nIIIII5\n");
+        assert_eq!(stats.quality_3p, 4);
+
+        let solexa = FastQRecord::<DnaSeq, Solexa>::parse("@read\nACGT\n+\nhh;;\n").unwrap();
+        let (trimmed, _) = Trimmer::new(vec![TrimStep::Trailing(2)]).trim_record(&solexa);
+        // Solexa -5 is an error probability of 0.76, below Phred 2
+        assert_eq!(trimmed.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "exactly one quality score per base")]
+    fn mismatched_lengths() {
+        let read = read("ACGT", "IIII");
+        let qualities = read.qualities().take(3).collect::<Vec<_>>();
+        let _ = Trimmer::new(vec![TrimStep::TrimNs]).trim_range(&read.sequence, &qualities);
+    }
+
+    #[test]
+    fn adapter() {
+        let adapter = TrimStep::Adapter {
+            sequence: "AGATCGGAAG".into(),
+            max_mismatch_rate: 0.1,
+            min_overlap: 3,
+        };
+        let (trimmed, stats) = Trimmer::new(vec![adapter, TrimStep::TrimNs])
+            .trim_record(&read("NACGTAGATCGG", "IIIIIIIIIIII"));
+        assert_eq!(trimmed.to_string(), read("ACGT", "IIII").to_string());
+        assert_eq!((stats.adapters_found, stats.adapter, stats.n), (1, 7, 1));
+    }
+}
@@ -1,4 +1,12 @@
 /// Genomic sequence types such as [`DnaSeq`](crate::genomics::genome::DnaSeq) and [`RnaSeq`](crate::genomics::genome::RnaSeq)
 pub mod genome;
+/// Intervals of sequence positions, counted from 1 with both ends included or from 0 with the end excluded
+pub mod interval;
+/// Fixed-length substrings of a [`DnaSeq`](crate::genomics::genome::DnaSeq) packed into integers
+pub mod kmer;
 /// Individual nucleotide type such as [`DNA`](crate::genomics::nucleotide::DNA) and [`RNA`](crate::genomics::nucleotide::RNA)
 pub mod nucleotide;
+/// Bottom-k MinHash sketches for estimating the distance between sequences
+pub mod sketch;
+/// Summary statistics such as base composition, GC content and N50
+pub mod stats;
@@ -1,4 +1,8 @@
-use super::nucleotide::{DNA, RNA};
+use super::{
+    interval::ZeroBased,
+    kmer::Kmers,
+    nucleotide::{DNA, RNA},
+};
 use crate::fasta::Sequence;
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
@@ -9,9 +13,35 @@ use std::{
 };
 
 /// A sequence of [`DNA`] nucleotides
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DnaSeq(Vec<DNA>);
 
+impl DnaSeq {
+    /// Borrows the nucleotides of this sequence as a slice
+    #[must_use]
+    pub fn as_slice(&self) -> &[DNA] {
+        &self.0
+    }
+
+    /// Returns an iterator over the nucleotides of this sequence
+    pub fn iter(&self) -> std::slice::Iter<'_, DNA> {
+        self.0.iter()
+    }
+
+    /// Returns an iterator over every `k`-mer of this sequence, see [`Kmers`]
+    pub fn kmers(&self, k: usize) -> Kmers<'_> {
+        Kmers::new(&self.0, k)
+    }
+
+    /// Borrows the nucleotides in `interval`, which may also be a [`OneBased`](super::interval::OneBased)
+    /// interval, or returns `None` if it runs past the end of the sequence
+    #[must_use]
+    pub fn slice(&self, interval: impl Into<ZeroBased>) -> Option<&[DNA]> {
+        let interval = interval.into();
+        self.0.get(interval.start..interval.end)
+    }
+}
+
 impl Sequence for DnaSeq {
     type Inner = DNA;
 
@@ -23,6 +53,10 @@ impl Sequence for DnaSeq {
         todo!()
     }
 
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
     const VALID_CHARS: &'static str = "0ACMGRSVTWYHKDBN";
 }
 
@@ -78,6 +112,16 @@ impl Display for DnaSeq {
 #[derive(Debug)]
 pub struct RnaSeq(Vec<RNA>);
 
+impl RnaSeq {
+    /// Borrows the nucleotides in `interval`, which may also be a [`OneBased`](super::interval::OneBased)
+    /// interval, or returns `None` if it runs past the end of the sequence
+    #[must_use]
+    pub fn slice(&self, interval: impl Into<ZeroBased>) -> Option<&[RNA]> {
+        let interval = interval.into();
+        self.0.get(interval.start..interval.end)
+    }
+}
+
 impl Sequence for RnaSeq {
     type Inner = RNA;
 
@@ -89,6 +133,10 @@ impl Sequence for RnaSeq {
         todo!()
     }
 
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
     const VALID_CHARS: &'static str = "0ACMGRSVUWYHKDBN";
 }
 
@@ -0,0 +1,199 @@
+use std::ops::{Range, RangeInclusive};
+
+/// An interval of positions counted from 1 and including both ends, as in GFF, SAM and genome browsers.
+///
+/// The interval `OneBased::new(1, 3)` covers the first three bases of a sequence,
+/// the same bases as `ZeroBased::new(0, 3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OneBased {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An interval of positions counted from 0 and excluding its end, as in BED and Rust slices.
+///
+/// The interval `ZeroBased::new(0, 3)` covers the first three bases of a sequence,
+/// the same bases as `OneBased::new(1, 3)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ZeroBased {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl OneBased {
+    /// # Panics
+    ///
+    /// This function will panic if `start` is 0, since positions are counted from 1
+    #[must_use]
+    pub const fn new(start: usize, end: usize) -> Self {
+        assert!(start >= 1, "1-based intervals cannot start at 0");
+        Self { start, end }
+    }
+
+    /// The number of positions in the interval
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            (self.end - self.start).saturating_add(1)
+        }
+    }
+
+    /// Returns `true` if the interval ends before it starts
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    /// Returns `true` if `position`, counted from 1, lies in the interval
+    #[must_use]
+    pub const fn contains(&self, position: usize) -> bool {
+        self.start <= position && position <= self.end
+    }
+
+    /// Returns `true` if the intervals share at least one position
+    #[must_use]
+    pub const fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// The same positions counted from 0 with the end excluded
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the interval was built with a start of 0 through its fields
+    #[must_use]
+    pub const fn to_zero_based(self) -> ZeroBased {
+        assert!(self.start >= 1, "1-based intervals cannot start at 0");
+        ZeroBased::new(self.start - 1, self.end)
+    }
+}
+
+impl ZeroBased {
+    #[must_use]
+    pub const fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The number of positions in the interval
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Returns `true` if the interval contains no positions
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Returns `true` if `position`, counted from 0, lies in the interval
+    #[must_use]
+    pub const fn contains(&self, position: usize) -> bool {
+        self.start <= position && position < self.end
+    }
+
+    /// Returns `true` if the intervals share at least one position
+    #[must_use]
+    pub const fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// The same positions counted from 1 with the end included
+    #[must_use]
+    pub const fn to_one_based(self) -> OneBased {
+        OneBased::new(self.start.saturating_add(1), self.end)
+    }
+}
+
+impl From<ZeroBased> for OneBased {
+    fn from(interval: ZeroBased) -> Self {
+        interval.to_one_based()
+    }
+}
+
+impl From<OneBased> for ZeroBased {
+    fn from(interval: OneBased) -> Self {
+        interval.to_zero_based()
+    }
+}
+
+impl From<RangeInclusive<usize>> for OneBased {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self::new(*range.start(), *range.end())
+    }
+}
+
+impl From<OneBased> for RangeInclusive<usize> {
+    fn from(interval: OneBased) -> Self {
+        interval.start..=interval.end
+    }
+}
+
+impl From<Range<usize>> for ZeroBased {
+    fn from(range: Range<usize>) -> Self {
+        Self::new(range.start, range.end)
+    }
+}
+
+impl From<ZeroBased> for Range<usize> {
+    fn from(interval: ZeroBased) -> Self {
+        interval.start..interval.end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::genomics::genome::DnaSeq;
+
+    #[test]
+    fn conversions() {
+        let one = OneBased::new(1, 3);
+        let zero = ZeroBased::new(0, 3);
+        assert_eq!(one.to_zero_based(), zero);
+        assert_eq!(zero.to_one_based(), one);
+        assert_eq!(one.len(), 3);
+        assert_eq!(zero.len(), 3);
+        assert!(one.contains(3) && !one.contains(0));
+        assert!(zero.contains(0) && !zero.contains(3));
+        assert!(OneBased::new(5, 4).is_empty());
+        assert!(ZeroBased::new(4, 4).is_empty());
+        assert!(one.overlaps(&OneBased::new(3, 10)));
+        assert!(!zero.overlaps(&ZeroBased::new(3, 10)));
+        assert_eq!(Range::from(zero), 0..3);
+        assert_eq!(RangeInclusive::from(one), 1..=3);
+        assert_eq!(OneBased::from(2..=4), OneBased::new(2, 4));
+        assert_eq!(OneBased::new(1, usize::MAX).len(), usize::MAX);
+        assert_eq!(OneBased::new(usize::MAX, usize::MAX).len(), 1);
+        assert_eq!(OneBased::new(5, 3).len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot start at 0")]
+    fn zero_start() {
+        let _ = OneBased::new(0, 3);
+    }
+
+    #[test]
+    fn slicing() {
+        let seq = "ACGTACGT"
+            .chars()
+            .map(|c| c.try_into().unwrap())
+            .collect::<DnaSeq>();
+        let slice = |bases: Option<&[_]>| {
+            bases.map(|bases| bases.iter().map(char::from).collect::<String>())
+        };
+        assert_eq!(
+            slice(seq.slice(OneBased::new(2, 4))).as_deref(),
+            Some("CGT")
+        );
+        assert_eq!(
+            slice(seq.slice(ZeroBased::new(1, 4))).as_deref(),
+            Some("CGT")
+        );
+        assert_eq!(slice(seq.slice(OneBased::new(8, 8))).as_deref(), Some("T"));
+        assert_eq!(seq.slice(OneBased::new(8, 9)), None);
+    }
+}
@@ -0,0 +1,156 @@
+use super::nucleotide::DNA;
+
+/// The longest `k` that can be packed into a [`Kmer`]
+pub const MAX_K: usize = 32;
+
+/// A `k`-mer of unambiguous nucleotides packed two bits per base, alongside its reverse complement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Kmer {
+    /// The 2-bit packed bases as read on the forward strand
+    pub forward: u64,
+    /// The 2-bit packed bases of the reverse complement
+    pub reverse: u64,
+}
+
+impl Kmer {
+    /// The strand-independent representation of this `k`-mer, the lesser of the forward and reverse encodings
+    #[must_use]
+    pub fn canonical(self) -> u64 {
+        self.forward.min(self.reverse)
+    }
+}
+
+/// Packs an unambiguous nucleotide into two bits, with `A = 0`, `C = 1`, `G = 2` and `T = 3`
+pub(crate) fn encode(base: DNA) -> Option<u64> {
+    Some(match base {
+        DNA::Adenine => 0,
+        DNA::Cytosine => 1,
+        DNA::Guanine => 2,
+        DNA::Thymine => 3,
+        _ => return None,
+    })
+}
+
+/// An iterator over the [`Kmer`]s of a sequence of [`DNA`], created by [`DnaSeq::kmers`](crate::genomics::genome::DnaSeq::kmers).
+///
+/// Any `k`-mer that would contain an ambiguous base or a gap is skipped.
+#[derive(Debug, Clone)]
+pub struct Kmers<'a> {
+    seq: &'a [DNA],
+    k: usize,
+    pos: usize,
+    filled: usize,
+    mask: u64,
+    current: Kmer,
+}
+
+impl<'a> Kmers<'a> {
+    /// # Panics
+    ///
+    /// This function will panic if `k` is 0 or greater than [`MAX_K`]
+    pub(crate) fn new(seq: &'a [DNA], k: usize) -> Self {
+        assert!(
+            (1..=MAX_K).contains(&k),
+            "k must be between 1 and {MAX_K}, got {k}"
+        );
+        let mask = if k == MAX_K {
+            u64::MAX
+        } else {
+            (1 << (2 * k)) - 1
+        };
+        Self {
+            seq,
+            k,
+            pos: 0,
+            filled: 0,
+            mask,
+            current: Kmer {
+                forward: 0,
+                reverse: 0,
+            },
+        }
+    }
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = Kmer;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&base) = self.seq.get(self.pos) {
+            self.pos += 1;
+            let Some(bits) = encode(base) else {
+                self.filled = 0;
+                continue;
+            };
+            self.current.forward = ((self.current.forward << 2) | bits) & self.mask;
+            self.current.reverse = (self.current.reverse >> 2) | ((3 - bits) << (2 * (self.k - 1)));
+            self.filled += 1;
+            if self.filled >= self.k {
+                return Some(self.current);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.seq.len() - self.pos + self.filled.min(self.k - 1);
+        (0, Some((remaining + 1).saturating_sub(self.k)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MAX_K;
+    use crate::tests::seq;
+
+    #[test]
+    fn packing() {
+        let kmers = seq("ACGT").kmers(2).collect::<Vec<_>>();
+        // AC, CG and GT, whose reverse complements are GT, CG and AC
+        assert_eq!(
+            kmers.iter().map(|kmer| kmer.forward).collect::<Vec<_>>(),
+            [0b0001, 0b0110, 0b1011]
+        );
+        assert_eq!(
+            kmers.iter().map(|kmer| kmer.reverse).collect::<Vec<_>>(),
+            [0b1011, 0b0110, 0b0001]
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        let canonical = |src| {
+            seq(src)
+                .kmers(5)
+                .map(|kmer| kmer.canonical())
+                .collect::<Vec<_>>()
+        };
+        let mut reverse = canonical("CATGGTAACCGT");
+        reverse.reverse();
+        assert_eq!(canonical("ACGGTTACCATG"), reverse);
+    }
+
+    #[test]
+    fn ambiguous() {
+        let kmers = seq("ACNGTA").kmers(2).collect::<Vec<_>>();
+        // AC, then GT and TA, with nothing spanning the N
+        assert_eq!(
+            kmers.iter().map(|kmer| kmer.forward).collect::<Vec<_>>(),
+            [0b0001, 0b1011, 0b1100]
+        );
+        assert_eq!(seq("ACGNA").kmers(3).count(), 1);
+        assert_eq!(seq("NNNN").kmers(1).count(), 0);
+    }
+
+    #[test]
+    fn longest() {
+        let kmers = seq(&"ACGT".repeat(9)).kmers(MAX_K).collect::<Vec<_>>();
+        assert_eq!(kmers.len(), 5);
+        assert_eq!(kmers[0].forward, 0x1b1b_1b1b_1b1b_1b1b);
+        // ACGT is its own reverse complement
+        assert_eq!(kmers[0].reverse, kmers[0].forward);
+        // The reverse complement of (CGTA)8 is (TACG)8
+        assert_eq!(kmers[1].reverse, kmers[3].forward);
+        assert_eq!(kmers[4], kmers[0]);
+    }
+}
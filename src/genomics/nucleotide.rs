@@ -52,6 +52,25 @@ pub enum DNA {
     Any = 0xF,
 }
 
+impl DNA {
+    /// Returns the Watson-Crick complement of this nucleotide.
+    ///
+    /// Because each bit of the representation is one of the four nucleobases, complementing
+    /// a degenerate base is the same as reversing its four bits, e.g. `Amino` (A or C)
+    /// becomes `Ketone` (T or G)
+    #[must_use]
+    pub fn complement(self) -> Self {
+        Self::try_from((self as u8).reverse_bits() >> 4)
+            .expect("Reversing a 4 bit value always produces a 4 bit value")
+    }
+
+    /// Returns `true` if this nucleotide is exactly one of Adenine, Cytosine, Guanine or Thymine
+    #[must_use]
+    pub fn is_unambiguous(self) -> bool {
+        (self as u8).count_ones() == 1
+    }
+}
+
 impl Display for DNA {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         write!(f, "{}", DNA_CODONS[*self as usize])
@@ -283,18 +302,18 @@ impl TryFrom<u8> for RNA {
 
 impl From<RNA> for char {
     fn from(rna: RNA) -> Self {
-        RNA_CODONS[rna as usize] as char
+        RNA_CODONS[rna as usize]
     }
 }
 
 impl From<&RNA> for char {
     fn from(rna: &RNA) -> Self {
-        RNA_CODONS[*rna as usize] as char
+        RNA_CODONS[*rna as usize]
     }
 }
 
 impl From<&mut RNA> for char {
     fn from(rna: &mut RNA) -> Self {
-        RNA_CODONS[*rna as usize] as char
+        RNA_CODONS[*rna as usize]
     }
 }
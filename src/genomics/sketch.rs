@@ -0,0 +1,298 @@
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use super::{genome::DnaSeq, kmer::MAX_K};
+use crate::fasta::Fasta;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum SketchError {
+    #[error("Sketches must share k and seed to be compared, got (k: {0}, seed: {1}) and (k: {2}, seed: {3})")]
+    MismatchedParameters(usize, u64, usize, u64),
+    #[error("Input was not a serialized MinHash sketch")]
+    InvalidFormat,
+    #[error("Unsupported sketch format version {0}")]
+    UnsupportedVersion(u8),
+    #[error(transparent)]
+    IoErr(#[from] std::io::Error),
+}
+
+const MAGIC: &[u8; 4] = b"TSMH";
+const FORMAT_VERSION: u8 = 1;
+
+/// A bottom-k MinHash sketch: the `size` smallest hashes of every canonical `k`-mer in one or more sequences.
+///
+/// Two sketches built with the same `k` and `seed` can be compared to estimate the
+/// Jaccard index of their `k`-mer sets, and from that the [Mash](https://doi.org/10.1186/s13059-016-0997-x) distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinHashSketch {
+    k: usize,
+    size: usize,
+    seed: u64,
+    hashes: BTreeSet<u64>,
+}
+
+impl MinHashSketch {
+    /// The seed Mash uses by default, for callers that have no reason to pick another
+    pub const DEFAULT_SEED: u64 = 42;
+
+    /// Creates an empty sketch that will keep the `size` smallest hashes of `k`-mers hashed with `seed`
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `k` is 0 or greater than [`MAX_K`], or if `size` is 0
+    #[must_use]
+    pub fn new(k: usize, size: usize, seed: u64) -> Self {
+        assert!(
+            (1..=MAX_K).contains(&k),
+            "k must be between 1 and {MAX_K}, got {k}"
+        );
+        assert!(size > 0, "A sketch must keep at least one hash");
+        Self {
+            k,
+            size,
+            seed,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    /// Sketches a single sequence
+    #[must_use]
+    pub fn from_seq(seq: &DnaSeq, k: usize, size: usize, seed: u64) -> Self {
+        let mut sketch = Self::new(k, size, seed);
+        sketch.add_seq(seq);
+        sketch
+    }
+
+    /// Sketches every record of a [`Fasta`] file as a single set of `k`-mers
+    #[must_use]
+    pub fn from_fasta(records: &[Fasta<DnaSeq>], k: usize, size: usize, seed: u64) -> Self {
+        let mut sketch = Self::new(k, size, seed);
+        for record in records {
+            sketch.add_seq(&record.sequence);
+        }
+        sketch
+    }
+
+    /// Adds every `k`-mer of `seq` to this sketch
+    pub fn add_seq(&mut self, seq: &DnaSeq) {
+        for kmer in seq.kmers(self.k) {
+            self.insert(hash(kmer.canonical(), self.seed));
+        }
+    }
+
+    /// Merges the hashes of `other` into this sketch, as if both inputs had been sketched together
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the sketches were built with a different `k` or seed
+    pub fn merge(&mut self, other: &Self) -> Result<(), SketchError> {
+        self.check_compatible(other)?;
+        for &hash in &other.hashes {
+            self.insert(hash);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, hash: u64) {
+        if self.hashes.len() < self.size {
+            self.hashes.insert(hash);
+        } else if self.hashes.last().is_some_and(|&largest| hash < largest)
+            && self.hashes.insert(hash)
+        {
+            self.hashes.pop_last();
+        }
+    }
+
+    /// The length of the hashed `k`-mers
+    #[must_use]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The maximum number of hashes this sketch keeps
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The seed of the `k`-mer hash function
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The hashes currently held, in ascending order
+    pub fn hashes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.hashes.iter().copied()
+    }
+
+    fn check_compatible(&self, other: &Self) -> Result<(), SketchError> {
+        if self.k == other.k && self.seed == other.seed {
+            Ok(())
+        } else {
+            Err(SketchError::MismatchedParameters(
+                self.k, self.seed, other.k, other.seed,
+            ))
+        }
+    }
+
+    /// Estimates the Jaccard index of the `k`-mer sets the two sketches were built from.
+    ///
+    /// The estimate is taken over the bottom `s` hashes of the union of both sketches,
+    /// where `s` is the smaller of the two sketch sizes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the sketches were built with a different `k` or seed
+    pub fn jaccard(&self, other: &Self) -> Result<f64, SketchError> {
+        self.check_compatible(other)?;
+        let size = self.size.min(other.size);
+        let (mut ours, mut theirs) = (
+            self.hashes.iter().peekable(),
+            other.hashes.iter().peekable(),
+        );
+        let (mut shared, mut seen) = (0_usize, 0_usize);
+        while seen < size {
+            match (ours.peek(), theirs.peek()) {
+                (Some(a), Some(b)) if a == b => {
+                    shared += 1;
+                    ours.next();
+                    theirs.next();
+                }
+                (Some(a), Some(b)) if a < b => {
+                    ours.next();
+                }
+                (Some(_), Some(_)) => {
+                    theirs.next();
+                }
+                (Some(_), None) => {
+                    ours.next();
+                }
+                (None, Some(_)) => {
+                    theirs.next();
+                }
+                (None, None) => break,
+            }
+            seen += 1;
+        }
+        Ok(if seen == 0 {
+            0.0
+        } else {
+            shared as f64 / seen as f64
+        })
+    }
+
+    /// Estimates the Mash distance, an approximation of the per-base mutation rate between the two inputs.
+    ///
+    /// Sketches that share no hashes have a distance of `1.0`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the sketches were built with a different `k` or seed
+    pub fn mash_distance(&self, other: &Self) -> Result<f64, SketchError> {
+        let jaccard = self.jaccard(other)?;
+        Ok(if jaccard == 0.0 {
+            1.0
+        } else {
+            (-1.0 / self.k as f64) * (2.0 * jaccard / (1.0 + jaccard)).ln()
+        })
+    }
+
+    /// Writes this sketch in a compact little-endian binary format readable by [`MinHashSketch::read_from`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing to `dst` fails
+    pub fn write_to(&self, dst: &mut impl Write) -> Result<(), SketchError> {
+        dst.write_all(MAGIC)?;
+        dst.write_all(&[FORMAT_VERSION, self.k as u8])?;
+        dst.write_all(&self.seed.to_le_bytes())?;
+        dst.write_all(&(self.size as u64).to_le_bytes())?;
+        dst.write_all(&(self.hashes.len() as u64).to_le_bytes())?;
+        for hash in &self.hashes {
+            dst.write_all(&hash.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a sketch previously written by [`MinHashSketch::write_to`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from `src` fails or if it does not contain a valid sketch
+    pub fn read_from(src: &mut impl Read) -> Result<Self, SketchError> {
+        fn read_u64(src: &mut impl Read) -> Result<u64, SketchError> {
+            let mut buf = [0; 8];
+            src.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        }
+
+        let mut header = [0; 6];
+        src.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(SketchError::InvalidFormat);
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(SketchError::UnsupportedVersion(header[4]));
+        }
+        let k = usize::from(header[5]);
+        let seed = read_u64(src)?;
+        let size = usize::try_from(read_u64(src)?).map_err(|_| SketchError::InvalidFormat)?;
+        let len = read_u64(src)?;
+        if !(1..=MAX_K).contains(&k) || size == 0 || len > size as u64 {
+            return Err(SketchError::InvalidFormat);
+        }
+        let hashes = (0..len)
+            .map(|_| read_u64(src))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        Ok(Self {
+            k,
+            size,
+            seed,
+            hashes,
+        })
+    }
+}
+
+/// Mixes a packed `k`-mer with the MurmurHash3 64-bit finalizer so that the bottom-k hashes are a uniform sample
+fn hash(kmer: u64, seed: u64) -> u64 {
+    let mut h = kmer ^ seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+#[cfg(test)]
+mod test {
+    use super::MinHashSketch;
+    use crate::tests::seq;
+
+    #[test]
+    fn identical_sequences() {
+        let a = MinHashSketch::from_seq(&seq("ACGTTGCAACGGATTACAGATTACA"), 5, 100, 42);
+        assert_eq!(a.jaccard(&a).unwrap(), 1.0);
+        assert_eq!(a.mash_distance(&a).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn reverse_complement_is_identical() {
+        let a = MinHashSketch::from_seq(&seq("AACCGGTTACGATCGA"), 4, 100, 42);
+        let b = MinHashSketch::from_seq(&seq("TCGATCGTAACCGGTT"), 4, 100, 42);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn round_trip() {
+        let a = MinHashSketch::from_seq(&seq("ACGTTGCAACGGATTACAGATTACA"), 7, 8, 3);
+        let mut buf = Vec::new();
+        a.write_to(&mut buf).unwrap();
+        assert_eq!(MinHashSketch::read_from(&mut buf.as_slice()).unwrap(), a);
+    }
+}
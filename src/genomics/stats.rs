@@ -0,0 +1,319 @@
+use std::ops::{Add, AddAssign, Index};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{genome::DnaSeq, nucleotide::DNA};
+use crate::fasta::{Fasta, Sequence};
+
+/// The number of times each of the 16 [`DNA`] symbols occurs in one or more sequences
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BaseComposition {
+    counts: [usize; 16],
+}
+
+impl BaseComposition {
+    /// Counts the nucleotides of a single sequence
+    #[must_use]
+    pub fn of(seq: &DnaSeq) -> Self {
+        seq.iter().copied().collect()
+    }
+
+    /// The number of nucleotides counted, including gaps
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns `true` if no nucleotides have been counted
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of nucleotides counted, excluding gaps
+    #[must_use]
+    pub fn bases(&self) -> usize {
+        self.len() - self[DNA::Gap]
+    }
+
+    /// The number of `N` symbols
+    #[must_use]
+    pub fn n_count(&self) -> usize {
+        self[DNA::Any]
+    }
+
+    /// The number of symbols other than A, C, G, T and gaps, including `N`
+    #[must_use]
+    pub fn ambiguous(&self) -> usize {
+        self.bases()
+            - [DNA::Adenine, DNA::Cytosine, DNA::Guanine, DNA::Thymine]
+                .into_iter()
+                .map(|base| self[base])
+                .sum::<usize>()
+    }
+
+    /// The fraction of non-gap symbols that are certainly G or C, i.e. `G`, `C` or `S`
+    #[must_use]
+    pub fn gc_fraction(&self) -> f64 {
+        self.fraction(self[DNA::Guanine] + self[DNA::Cytosine] + self[DNA::Strong])
+    }
+
+    /// The fraction of non-gap symbols that are certainly A or T, i.e. `A`, `T` or `W`
+    #[must_use]
+    pub fn at_fraction(&self) -> f64 {
+        self.fraction(self[DNA::Adenine] + self[DNA::Thymine] + self[DNA::Weak])
+    }
+
+    /// The expected GC fraction when each ambiguity code contributes the share of its bases that are G or C,
+    /// e.g. `S` counts fully, `N` and `R` count one half, `B` counts two thirds, and `W` does not count
+    #[must_use]
+    pub fn weighted_gc_fraction(&self) -> f64 {
+        const STRONG: u8 = DNA::Strong as u8;
+        let weighted = (1..16_u8)
+            .map(|bits| {
+                (bits & STRONG).count_ones() as f64 / bits.count_ones() as f64
+                    * self.counts[usize::from(bits)] as f64
+            })
+            .sum::<f64>();
+        if self.bases() == 0 {
+            0.0
+        } else {
+            weighted / self.bases() as f64
+        }
+    }
+
+    fn fraction(&self, count: usize) -> f64 {
+        if self.bases() == 0 {
+            0.0
+        } else {
+            count as f64 / self.bases() as f64
+        }
+    }
+}
+
+impl Index<DNA> for BaseComposition {
+    type Output = usize;
+
+    fn index(&self, index: DNA) -> &Self::Output {
+        &self.counts[index as usize]
+    }
+}
+
+impl FromIterator<DNA> for BaseComposition {
+    fn from_iter<T: IntoIterator<Item = DNA>>(iter: T) -> Self {
+        let mut composition = Self::default();
+        for base in iter {
+            composition.counts[base as usize] += 1;
+        }
+        composition
+    }
+}
+
+impl Add for BaseComposition {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for BaseComposition {
+    fn add_assign(&mut self, rhs: Self) {
+        for (count, other) in self.counts.iter_mut().zip(rhs.counts) {
+            *count += other;
+        }
+    }
+}
+
+/// Computes the [`BaseComposition::gc_fraction`] of every `window`-sized window of `seq`, advancing `step` bases at a time.
+///
+/// A final window shorter than `window` is not reported.
+///
+/// # Panics
+///
+/// This function will panic if `window` or `step` is 0
+#[must_use]
+pub fn gc_profile(seq: &DnaSeq, window: usize, step: usize) -> Vec<f64> {
+    assert!(window > 0 && step > 0, "window and step must be non-zero");
+    let bases = seq.as_slice();
+    if bases.len() < window {
+        return Vec::new();
+    }
+    let mut composition = bases[..window].iter().copied().collect::<BaseComposition>();
+    let mut profile = vec![composition.gc_fraction()];
+    let mut start = 0;
+    while start + step + window <= bases.len() {
+        if step < window {
+            for &base in &bases[start..start + step] {
+                composition.counts[base as usize] -= 1;
+            }
+            for &base in &bases[start + window..start + window + step] {
+                composition.counts[base as usize] += 1;
+            }
+        } else {
+            composition = bases[start + step..start + step + window]
+                .iter()
+                .copied()
+                .collect();
+        }
+        start += step;
+        profile.push(composition.gc_fraction());
+    }
+    profile
+}
+
+/// Summary statistics for a set of sequences, such as the records of a FASTA file or an assembly
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeqStats {
+    /// The length of every sequence, longest first
+    lengths: Vec<usize>,
+    /// The combined base composition of every sequence
+    pub composition: BaseComposition,
+}
+
+impl SeqStats {
+    /// Computes the statistics of a single sequence
+    #[must_use]
+    pub fn of(seq: &DnaSeq) -> Self {
+        Self {
+            lengths: vec![seq.len()],
+            composition: BaseComposition::of(seq),
+        }
+    }
+
+    /// Computes the statistics of every record of a [`Fasta`] file
+    #[must_use]
+    pub fn from_fasta(records: &[Fasta<DnaSeq>]) -> Self {
+        #[cfg(feature = "rayon")]
+        let composition = records
+            .par_iter()
+            .map(|record| BaseComposition::of(&record.sequence))
+            .reduce(BaseComposition::default, Add::add);
+        #[cfg(not(feature = "rayon"))]
+        let composition = records
+            .iter()
+            .map(|record| BaseComposition::of(&record.sequence))
+            .fold(BaseComposition::default(), Add::add);
+        let mut lengths = records
+            .iter()
+            .map(|record| record.sequence.len())
+            .collect::<Vec<_>>();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        Self {
+            lengths,
+            composition,
+        }
+    }
+
+    /// The number of sequences
+    #[must_use]
+    pub fn num_seqs(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// The sum of the lengths of every sequence
+    #[must_use]
+    pub fn total_len(&self) -> usize {
+        self.composition.len()
+    }
+
+    /// The length of the shortest sequence
+    #[must_use]
+    pub fn min_len(&self) -> Option<usize> {
+        self.lengths.last().copied()
+    }
+
+    /// The length of the longest sequence
+    #[must_use]
+    pub fn max_len(&self) -> Option<usize> {
+        self.lengths.first().copied()
+    }
+
+    /// The mean sequence length
+    #[must_use]
+    pub fn mean_len(&self) -> Option<f64> {
+        (!self.lengths.is_empty()).then(|| self.total_len() as f64 / self.lengths.len() as f64)
+    }
+
+    /// Returns `(Nx, Lx)` for a given `percent`: the length of the shortest sequence among the longest sequences
+    /// that together cover at least `percent`% of the total length, and how many of those sequences there are.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `percent` is not in the range `0.0..=100.0`
+    #[must_use]
+    pub fn nx(&self, percent: f64) -> Option<(usize, usize)> {
+        assert!(
+            (0.0..=100.0).contains(&percent),
+            "percent must be between 0 and 100, got {percent}"
+        );
+        let target = self.total_len() as f64 * percent / 100.0;
+        let mut covered = 0;
+        for (count, &len) in self.lengths.iter().enumerate() {
+            covered += len;
+            if covered as f64 >= target {
+                return Some((len, count + 1));
+            }
+        }
+        None
+    }
+
+    /// The N50 of these sequences, see [`SeqStats::nx`]
+    #[must_use]
+    pub fn n50(&self) -> Option<usize> {
+        self.nx(50.0).map(|(n, _)| n)
+    }
+
+    /// The L50 of these sequences, see [`SeqStats::nx`]
+    #[must_use]
+    pub fn l50(&self) -> Option<usize> {
+        self.nx(50.0).map(|(_, l)| l)
+    }
+
+    /// The N90 of these sequences, see [`SeqStats::nx`]
+    #[must_use]
+    pub fn n90(&self) -> Option<usize> {
+        self.nx(90.0).map(|(n, _)| n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{gc_profile, BaseComposition, SeqStats};
+    use crate::{fasta::Fasta, tests::seq};
+
+    #[test]
+    fn composition() {
+        let comp = BaseComposition::of(&seq("ACGTSWNN"));
+        assert_eq!(comp.len(), 8);
+        assert_eq!(comp.n_count(), 2);
+        assert_eq!(comp.ambiguous(), 4);
+        assert_eq!(comp.gc_fraction(), 3.0 / 8.0);
+        assert_eq!(comp.at_fraction(), 3.0 / 8.0);
+        assert_eq!(comp.weighted_gc_fraction(), 4.0 / 8.0);
+    }
+
+    #[test]
+    fn profile() {
+        assert_eq!(gc_profile(&seq("GGAAGGAA"), 4, 2), vec![0.5, 0.5, 0.5]);
+        assert_eq!(gc_profile(&seq("GGGCAAAT"), 4, 4), vec![1.0, 0.0]);
+    }
+
+    #[test]
+    fn n50() {
+        let records = [10, 2, 5, 3]
+            .into_iter()
+            .map(|len| Fasta {
+                description: None,
+                sequence: seq(&"A".repeat(len)),
+            })
+            .collect::<Vec<_>>();
+        let stats = SeqStats::from_fasta(&records);
+        assert_eq!(stats.total_len(), 20);
+        assert_eq!(stats.nx(50.0), Some((10, 1)));
+        assert_eq!(stats.nx(90.0), Some((3, 3)));
+        assert_eq!(stats.min_len(), Some(2));
+    }
+}
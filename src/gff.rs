@@ -1,35 +1,47 @@
 use std::{
+    borrow::{Borrow, Cow},
+    collections::HashMap,
     fmt,
-    io::Read,
-    ops::{Deref, DerefMut, Range},
+    io::{BufRead, BufReader, Read, Write},
+    ops::{Deref, DerefMut},
     str::FromStr,
 };
 
 use attr::AttributeSet;
 use meta::Metadata;
 use miette::Diagnostic;
-use nom::{
-    branch::alt,
-    character::complete::{line_ending, not_line_ending},
-    combinator::{map, map_res, value},
-    error::VerboseError,
-    sequence::{delimited, tuple},
-    Parser,
-};
-use nom_supreme::{
-    final_parser::final_parser, multi::parse_separated_terminated_res, tag::complete::tag,
-};
+use nom::{combinator::map_res, error::VerboseError, Parser};
+use nom_supreme::final_parser::final_parser;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use reader::Line;
+use reader::{GffItem, GffReader};
 use thiserror::Error;
 
-use crate::NomResult;
+use crate::{
+    fasta::{Fasta, FastaError},
+    genomics::{genome::DnaSeq, interval::OneBased},
+    NomResult,
+};
 
 use self::parsers::ParseError;
 
+pub mod alignment;
 pub mod attr;
+pub mod borrowed;
+pub mod extract;
+pub mod graph;
+pub mod gtf;
+pub mod index;
 pub mod meta;
+pub mod model;
+pub mod ontology;
 mod parsers;
+pub mod reader;
 #[cfg(test)]
 mod test;
+pub mod validate;
 
 #[derive(Debug, Error, Diagnostic)]
 pub enum GffError {
@@ -39,14 +51,24 @@ pub enum GffError {
     DuplicateSequence,
     #[error("Invalid Genome Build")]
     InvalidGenomeBuild,
+    #[error("URIs must start with a scheme and only contain the characters allowed by RFC 3986")]
+    InvalidUri,
+    #[error("The {directive} directive must have a value")]
+    MissingDirectiveValue { directive: Box<str> },
     #[error(transparent)]
     ParseError(#[from] parsers::ParseError),
     #[error("Failed to decode an escaped string")]
     StringDecodeErr,
+    #[error("Escape at byte {at} must be followed by two hex digits")]
+    TruncatedEscape { at: usize },
+    #[error("Invalid escape {escape} at byte {at}")]
+    InvalidEscape { at: usize, escape: Box<str> },
     #[error("Strand must be one of [. - + ?]")]
     InvalidStrand,
     #[error(transparent)]
     IoErr(#[from] std::io::Error),
+    #[error(transparent)]
+    FastaError(#[from] FastaError),
     #[error("Most Meta-Attributes must be unique")]
     DuplicateMetaAttribute,
     #[error("Line was malformed and parsing could not continue")]
@@ -61,56 +83,200 @@ pub enum GffError {
     ReservedAttribute,
     #[error("Target Attribute must be in the form [target_id start end strand]")]
     MalformedTarget,
+    #[error("Dbxref and Ontology_term values must be in the form DB:ID")]
+    MalformedDbXref,
+    #[error("GTF values cannot contain quotes, semicolons or control characters, as in {value}")]
+    InvalidGtfValue { value: Box<str> },
+    #[error("Frameshifts cannot be written in a CIGAR string")]
+    FrameshiftInCigar,
+    #[error("CIGAR strings must be a list of lengths each followed by one of [M I D N S H P = X]")]
+    InvalidCigar,
+    #[error("Parent {parent} of feature {id} does not exist")]
+    DanglingParent { id: Box<str>, parent: Box<str> },
+    #[error("Feature {id} is its own ancestor")]
+    FeatureCycle { id: Box<str> },
+    #[error("Sequence {seq_id} was not found")]
+    MissingSequence { seq_id: Box<str> },
+    #[error("A feature ending at {end} lies outside of sequence {seq_id}")]
+    FeatureOutOfBounds { seq_id: Box<str>, end: usize },
+    #[error("Codon {codon} of {id} cannot be translated")]
+    Untranslatable { id: Box<str>, codon: Box<str> },
+    #[error("Error on line {line}")]
+    AtLine {
+        line: usize,
+        #[source]
+        source: Box<GffError>,
+    },
 }
 
 /// A Generic Feature Format Version 3 file including both metadata and entries
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct GFF {
     /// A list of the [`Metadata`]
     pub metadata: Metadata,
     /// A list of the entries
     pub entries: Vec<Entry>,
+    /// The sequences from the `##FASTA` section at the end of the file
+    pub sequences: Vec<Fasta<DnaSeq>>,
 }
 
 impl GFF {
-    /// Attempts to parse the given [`Reader`](std::io::Read) as a GFFv3-formatted input
+    /// Attempts to parse the given [`Reader`](std::io::Read) as a GFFv3-formatted input,
+    /// reading it one line at a time.
+    ///
+    /// With the `rayon` feature, parsing a string that already holds the whole file with
+    /// [`str::parse`] spreads the entries across threads instead.
+    /// To read entries one at a time, see [`GffReader`](reader::GffReader)
     #[tracing::instrument(skip_all)]
     pub fn read_from(src: &mut impl Read) -> Result<Self, GffError> {
-        std::io::read_to_string(src)?.parse()
+        Self::from_reader(GffReader::new(BufReader::new(src)))
     }
 
+    /// Writes this file in the GFFv3 format to the given [`Writer`](std::io::Write)
+    #[tracing::instrument(skip_all)]
+    pub fn write_to(&self, dst: &mut impl Write) -> Result<(), GffError> {
+        write!(dst, "{self}")?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "rayon"))]
     fn parse(src: &str) -> Result<Self, GffError> {
-        final_parser::<_, _, VerboseError<&str>, ParseError>(parse_separated_terminated_res(
-            not_line_ending,
-            line_ending,
-            delimited(line_ending, tag("###"), line_ending),
-            GFF::default,
-            Self::parse_line,
-        ))(src)
-        .map_err(Into::into)
-    }
-
-    #[tracing::instrument(skip(self))]
-    fn parse_line(mut self, line: &str) -> Result<Self, GffError> {
-        final_parser::<_, _, VerboseError<&str>, ParseError>(alt((
-            //parse meta
-            map_res(
-                tuple((
-                    alt((value(false, tag("##")), value(true, tag("#!")))),
-                    not_line_ending,
-                )),
-                |(is_domain, meta)| -> Result<(), GffError> {
-                    self.metadata.parse_line(is_domain, meta)
-                },
-            ),
-            map(Entry::parse, |entry| {
-                self.entries.push(entry);
-            }),
-        )))(line)?;
-        Ok(self)
+        Self::from_reader(GffReader::new(src.as_bytes()))
+    }
+
+    /// Reads the directives in order, then parses the entry lines across threads
+    #[cfg(feature = "rayon")]
+    fn parse(src: &str) -> Result<Self, GffError> {
+        let at_line = |line, source: GffError| GffError::AtLine {
+            line,
+            source: Box::new(source),
+        };
+        let mut metadata = Metadata::default();
+        let mut entry_lines = Vec::new();
+        let mut fasta = None;
+        let mut offset = 0;
+        for (i, raw) in src.split_inclusive('\n').enumerate() {
+            let directive = match Line::classify(raw) {
+                Line::Fasta { header } => {
+                    // The sequences run to the end of the file
+                    let start = if header { offset } else { offset + raw.len() };
+                    fasta = Some((i + 1, &src[start..]));
+                    break;
+                }
+                Line::Directive(directive) => metadata.parse_line(false, directive),
+                Line::DomainDirective(directive) => metadata.parse_line(true, directive),
+                Line::Entry(entry) => {
+                    entry_lines.push((i + 1, entry));
+                    Ok(())
+                }
+                Line::Blank | Line::ResolutionBarrier | Line::Comment(_) => Ok(()),
+            };
+            offset += raw.len();
+            directive.map_err(|e| at_line(i + 1, e))?;
+        }
+
+        // Collecting every result first reports the earliest error, rather than whichever thread failed first
+        let entries = entry_lines
+            .par_iter()
+            .map(|&(line, entry)| {
+                let mut entry = entry.parse::<Entry>().map_err(|e| at_line(line, e))?;
+                entry.line = Some(line);
+                Ok::<_, GffError>(entry)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        let sequences = match fasta {
+            Some((line, fasta)) if !fasta.trim().is_empty() => {
+                Fasta::parse(fasta).map_err(|e| at_line(line, e.into()))?
+            }
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            metadata,
+            entries,
+            sequences,
+        })
+    }
+
+    fn from_reader<R: BufRead>(mut reader: GffReader<R>) -> Result<Self, GffError> {
+        let mut entries = Vec::new();
+        let mut sequences = Vec::new();
+        for item in reader.by_ref() {
+            match item? {
+                (_, GffItem::Entry(entry)) => entries.push(*entry),
+                (_, GffItem::Sequence(record)) => sequences.push(*record),
+                _ => {}
+            }
+        }
+        Ok(Self {
+            metadata: reader.into_metadata(),
+            entries,
+            sequences,
+        })
+    }
+
+    /// Returns, for each entry, whether a `###` separator can be written before it.
+    ///
+    /// A separator is written before each top level feature that no earlier entry refers forward to,
+    /// and that refers to no earlier entry itself.
+    fn separators(&self) -> Vec<bool> {
+        let mut spans: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let attrs = &entry.attrs;
+            let ids = attrs
+                .id
+                .iter()
+                .chain(attrs.parent.iter().flatten())
+                .chain(attrs.derives_from.iter());
+            for id in ids {
+                spans
+                    .entry(Borrow::<str>::borrow(id))
+                    .and_modify(|(_, last)| *last = i)
+                    .or_insert((i, i));
+            }
+        }
+        // The number of features that are still open across the boundary before each entry
+        let mut open = vec![0isize; self.entries.len() + 1];
+        for &(first, last) in spans.values() {
+            open[first + 1] += 1;
+            open[last + 1] -= 1;
+        }
+        let mut depth = 0;
+        self.entries
+            .iter()
+            .zip(open)
+            .enumerate()
+            .map(|(i, (entry, change))| {
+                depth += change;
+                i > 0 && depth == 0 && entry.attrs.parent.is_none()
+            })
+            .collect()
     }
 }
 
+impl fmt::Display for GFF {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.metadata)?;
+        for (entry, separated) in self.entries.iter().zip(self.separators()) {
+            if separated {
+                writeln!(f, "###")?;
+            }
+            writeln!(f, "{entry}")?;
+        }
+        if !self.sequences.is_empty() {
+            writeln!(f, "##FASTA")?;
+            for record in &self.sequences {
+                write!(f, "{record}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a whole GFF3 file held in memory.
+///
+/// With the `rayon` feature, the directives are read in order first and the entries are then parsed in parallel
 impl FromStr for GFF {
     type Err = GffError;
 
@@ -119,16 +285,34 @@ impl FromStr for GFF {
         Self::parse(s)
     }
 }
+
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub seq_id: UnescapedString,
     pub source: UnescapedString,
     pub feature_type: UnescapedString,
-    pub range: Range<usize>,
+    /// The positions of the feature, from the start and end columns
+    pub range: OneBased,
     pub score: Option<f64>,
     pub strand: Option<Strand>,
     pub phase: Option<u8>,
     pub attrs: AttributeSet,
+    /// The (1-based) line this entry was read from, if it was read from a file
+    pub line: Option<usize>,
+}
+
+/// Entries are equal if their columns are, wherever in a file they were read from
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq_id == other.seq_id
+            && self.source == other.source
+            && self.feature_type == other.feature_type
+            && self.range == other.range
+            && self.score == other.score
+            && self.strand == other.strand
+            && self.phase == other.phase
+            && self.attrs == other.attrs
+    }
 }
 
 impl Entry {
@@ -143,17 +327,45 @@ impl Entry {
                     seq_id: UnescapedString::new(seq)?,
                     source: UnescapedString::new(source)?,
                     feature_type: UnescapedString::new(feature)?,
-                    range: range_s..range_e,
+                    range: OneBased::new(range_s, range_e),
                     score,
                     strand: strand.map(Strand::parse).transpose()?,
                     phase,
                     attrs: AttributeSet::parse(attrs)?,
+                    line: None,
                 })
             },
         ).parse(src)
     }
 }
 
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t",
+            Escaped(&self.seq_id, is_seq_id_char),
+            Escaped(&self.source, is_column_char),
+            Escaped(&self.feature_type, is_column_char),
+            self.range.start,
+            self.range.end
+        )?;
+        match self.score {
+            Some(score) => write!(f, "{score}\t")?,
+            None => f.write_str(".\t")?,
+        }
+        match self.strand {
+            Some(ref strand) => write!(f, "{strand}\t")?,
+            None => f.write_str(".\t")?,
+        }
+        match self.phase {
+            Some(phase) => write!(f, "{phase}\t")?,
+            None => f.write_str(".\t")?,
+        }
+        write!(f, "{}", self.attrs)
+    }
+}
+
 impl FromStr for Entry {
     type Err = GffError;
 
@@ -183,26 +395,76 @@ impl Strand {
     }
 }
 
+impl fmt::Display for Strand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Self::Positive => '+',
+            Self::Negative => '-',
+            Self::Unknown => '?',
+        };
+        write!(f, "{c}")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct UnescapedString(Box<str>);
 
 impl UnescapedString {
+    /// Decodes the `%XX` escapes of a GFF3 string, in which multi-byte characters are escaped byte by byte
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a `%` is not followed by two hex digits,
+    /// or if the decoded bytes are not valid UTF-8
     #[tracing::instrument(name = "UnescapedString::new", level = "trace")]
     pub fn new(src: &str) -> Result<Self, GffError> {
-        if src.contains('%') {
-            let mut escaped = src.to_owned();
-            while let Some(at) = escaped.find('%') {
-                let old = &escaped[at..][..3];
-                let byte = &[
-                    u8::from_str_radix(&old[1..3], 16).map_err(|_| GffError::StringDecodeErr)?
-                ];
-                let new = std::str::from_utf8(byte).map_err(|_| GffError::StringDecodeErr)?;
-                escaped = escaped.replace(old, new);
+        Self::decode(src).map(|decoded| Self(decoded.into()))
+    }
+
+    /// Decodes the `%XX` escapes of a GFF3 string like [`UnescapedString::new`],
+    /// borrowing `src` unless it has any
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a `%` is not followed by two hex digits,
+    /// or if the decoded bytes are not valid UTF-8
+    pub fn decode(src: &str) -> Result<Cow<'_, str>, GffError> {
+        if !src.contains('%') {
+            return Ok(Cow::Borrowed(src));
+        }
+        let bytes = src.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'%' {
+                decoded.push(bytes[i]);
+                i += 1;
+                continue;
             }
-            Ok(Self(escaped.into_boxed_str()))
-        } else {
-            Ok(Self(src.into()))
+            let escape = bytes
+                .get(i + 1..i + 3)
+                .ok_or(GffError::TruncatedEscape { at: i })?;
+            let byte = std::str::from_utf8(escape)
+                .ok()
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| GffError::InvalidEscape {
+                    at: i,
+                    escape: String::from_utf8_lossy(&bytes[i..i + 3]).into(),
+                })?;
+            decoded.push(byte);
+            i += 3;
         }
+        String::from_utf8(decoded)
+            .map(Cow::Owned)
+            .map_err(|_| GffError::StringDecodeErr)
+    }
+
+    /// Percent-encodes this string so it can be written in any column of a GFF3 file,
+    /// the reverse of [`UnescapedString::new`]
+    #[must_use]
+    pub fn escape(&self) -> String {
+        Escaped(self, is_seq_id_char).to_string()
     }
 }
 
@@ -237,3 +499,42 @@ impl DerefMut for UnescapedString {
         self.0.deref_mut()
     }
 }
+
+/// Writes a string with every character rejected by the predicate percent-encoded,
+/// the reverse of [`UnescapedString::new`]
+pub(crate) struct Escaped<'a>(pub(crate) &'a str, pub(crate) fn(char) -> bool);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(mut src, keep) = *self;
+        // Write each run of kept characters at once, then the escaped character after it
+        while let Some((at, c)) = src.char_indices().find(|&(_, c)| !keep(c)) {
+            f.write_str(&src[..at])?;
+            for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                write!(f, "%{byte:02X}")?;
+            }
+            src = &src[at + c.len_utf8()..];
+        }
+        f.write_str(src)
+    }
+}
+
+/// Characters that can be written unescaped in a sequence ID
+pub(crate) fn is_seq_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || ".:^*$@!+_?-|".contains(c)
+}
+
+/// Characters that can be written unescaped in the source and type columns and in directives
+pub(crate) fn is_column_char(c: char) -> bool {
+    !c.is_control() && c != '%'
+}
+
+/// Characters that can be written unescaped in an attribute value
+pub(crate) fn is_attribute_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || r#".:^*$@!+_?-| "'()/,"#.contains(c)
+}
+
+/// Characters that can be written unescaped in one value of an attribute with several
+pub(crate) fn is_list_char(c: char) -> bool {
+    is_attribute_char(c) && c != ','
+}
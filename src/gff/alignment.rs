@@ -0,0 +1,219 @@
+use super::{
+    attr::{GapKind, TargetAttr},
+    ontology::Term,
+    Entry, GffError, Strand,
+};
+use crate::genomics::interval::OneBased;
+
+/// A run of aligned positions, as offsets from the start of the reference and of the target
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    reference: usize,
+    target: usize,
+    /// The length on the target; the reference covers `scale` times as many positions
+    len: usize,
+}
+
+/// The alignment of an entry, such as a `cDNA_match`, to its `Target`, following its `Gap`.
+///
+/// The reference is the sequence the entry lies on, and is walked from start to end while the
+/// operations of the `Gap` are applied in order. The target is walked from its end instead if one of the
+/// entry and the target is on the [`Strand::Negative`] strand and the other is not.
+///
+/// For a kind of `protein_match` the target is a protein, so every `M`, `I` and `D` operation covers a
+/// codon of the reference, while `F` and `R` move forward and back on the reference by single nucleotides
+#[derive(Debug, Clone)]
+pub struct Alignment<'a> {
+    reference: OneBased,
+    target: &'a TargetAttr,
+    reverse: bool,
+    scale: usize,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Alignment<'a> {
+    /// Aligns `reference` to `target`, with `scale` positions of the reference for each of the target.
+    /// Without a `gap`, every position is matched
+    #[must_use]
+    pub fn new(
+        reference: OneBased,
+        reference_strand: Option<&Strand>,
+        target: &'a TargetAttr,
+        gap: Option<&[(GapKind, usize)]>,
+        scale: usize,
+    ) -> Self {
+        let ungapped = [(GapKind::Match, target.range().len())];
+        let mut blocks = Vec::new();
+        let (mut reference_offset, mut target_offset) = (0usize, 0);
+        for &(kind, len) in gap.unwrap_or(&ungapped) {
+            match kind {
+                GapKind::Match => {
+                    blocks.push(Block {
+                        reference: reference_offset,
+                        target: target_offset,
+                        len,
+                    });
+                    reference_offset += len * scale;
+                    target_offset += len;
+                }
+                GapKind::Insert => target_offset += len,
+                GapKind::Delete => reference_offset += len * scale,
+                GapKind::FwdFrameShift => reference_offset += len,
+                GapKind::RevFrameShift => reference_offset = reference_offset.saturating_sub(len),
+            }
+        }
+        let is_negative = |strand: Option<&Strand>| strand == Some(&Strand::Negative);
+        Self {
+            reference,
+            target,
+            reverse: is_negative(reference_strand) != is_negative(target.strand()),
+            scale,
+            blocks,
+        }
+    }
+
+    /// The target this alignment is to
+    #[must_use]
+    pub fn target(&self) -> &'a TargetAttr {
+        self.target
+    }
+
+    /// The position of the target aligned to `position` on the reference,
+    /// or `None` if it is not aligned, such as when it lies in a deletion
+    #[must_use]
+    pub fn to_target(&self, position: usize) -> Option<usize> {
+        let offset = position.checked_sub(self.reference.start)?;
+        if position > self.reference.end {
+            return None;
+        }
+        let block = self.blocks.iter().find(|block| {
+            block.reference <= offset && offset < block.reference + block.len * self.scale
+        })?;
+        let target_offset = block.target + (offset - block.reference) / self.scale;
+        let target = self.target.range();
+        Some(if self.reverse {
+            target.end.checked_sub(target_offset)?
+        } else {
+            target.start + target_offset
+        })
+    }
+
+    /// The positions of the reference aligned to `position` on the target, which for a protein
+    /// are the three bases of its codon, or `None` if it is not aligned, such as when it lies in an insertion
+    #[must_use]
+    pub fn to_reference(&self, position: usize) -> Option<OneBased> {
+        let target = self.target.range();
+        if !target.contains(position) {
+            return None;
+        }
+        let offset = if self.reverse {
+            target.end - position
+        } else {
+            position - target.start
+        };
+        let block = self
+            .blocks
+            .iter()
+            .find(|block| block.target <= offset && offset < block.target + block.len)?;
+        let start = self.reference.start + block.reference + (offset - block.target) * self.scale;
+        Some(OneBased::new(start, start + self.scale - 1))
+    }
+}
+
+impl Entry {
+    /// The number of positions of this entry for each position of its `Target`,
+    /// which is 3 for kinds of `protein_match`, aligned to proteins, and 1 for everything else
+    #[must_use]
+    pub fn target_scale(&self) -> usize {
+        let protein_match = Term::get("protein_match").expect("protein_match is an embedded term");
+        if self.term().is_some_and(|term| term.is_a(protein_match)) {
+            3
+        } else {
+            1
+        }
+    }
+
+    /// The alignment of this entry to its `Target`, if it has one, scaled by [`Entry::target_scale`]
+    #[must_use]
+    pub fn alignment(&self) -> Option<Alignment<'_>> {
+        let target = self.attrs.target.as_ref()?;
+        Some(Alignment::new(
+            self.range,
+            self.strand.as_ref(),
+            target,
+            self.attrs.gap.as_deref(),
+            self.target_scale(),
+        ))
+    }
+}
+
+/// Writes a `Gap` as a SAM CIGAR string, such as `M8 D3 M6` as `8M3D6M`,
+/// taking the entry's sequence as the SAM reference and the target as the query.
+///
+/// CIGAR lengths count bases of the reference, so each length is multiplied by `scale`,
+/// which is the [`Entry::target_scale`] of the entry: `M10` of a `protein_match` becomes `30M`
+///
+/// # Errors
+///
+/// This function will return an error if the gap has frameshifts, which CIGAR strings cannot represent
+pub fn to_cigar(gap: &[(GapKind, usize)], scale: usize) -> Result<String, GffError> {
+    gap.iter()
+        .map(|&(kind, len)| {
+            let op = match kind {
+                GapKind::Match => 'M',
+                GapKind::Insert => 'I',
+                GapKind::Delete => 'D',
+                GapKind::FwdFrameShift | GapKind::RevFrameShift => {
+                    return Err(GffError::FrameshiftInCigar)
+                }
+            };
+            Ok(format!("{}{op}", len * scale))
+        })
+        .collect()
+}
+
+/// Reads a `Gap` from a SAM CIGAR string, the reverse of [`to_cigar`], dividing each length by `scale`.
+///
+/// Sequence matches and mismatches (`=` and `X`) become matches, skipped regions (`N`) become deletions,
+/// adjacent operations of the same kind are merged, and clipping and padding are left out
+///
+/// # Errors
+///
+/// This function will return an error if the CIGAR string is malformed,
+/// or if the length of a merged operation is not a multiple of `scale`
+pub fn from_cigar(cigar: &str, scale: usize) -> Result<Vec<(GapKind, usize)>, GffError> {
+    let mut gap: Vec<(GapKind, usize)> = Vec::new();
+    let mut rest = cigar;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or(GffError::InvalidCigar)?;
+        let len = rest[..digits]
+            .parse::<usize>()
+            .map_err(|_| GffError::InvalidCigar)?;
+        let op = rest[digits..]
+            .chars()
+            .next()
+            .ok_or(GffError::InvalidCigar)?;
+        rest = &rest[digits + op.len_utf8()..];
+        let kind = match op {
+            'M' | '=' | 'X' => GapKind::Match,
+            'I' => GapKind::Insert,
+            'D' | 'N' => GapKind::Delete,
+            'S' | 'H' | 'P' => continue,
+            _ => return Err(GffError::InvalidCigar),
+        };
+        match gap.last_mut() {
+            Some((last, last_len)) if *last == kind => *last_len += len,
+            _ => gap.push((kind, len)),
+        }
+    }
+    gap.into_iter()
+        .map(|(kind, len)| {
+            if scale == 0 || len % scale != 0 {
+                return Err(GffError::InvalidCigar);
+            }
+            Ok((kind, len / scale))
+        })
+        .collect()
+}
@@ -2,13 +2,19 @@ use nom::{
     branch::alt,
     bytes::complete::{take_until, take_while},
     character::complete::{char, one_of},
-    combinator::{eof, map, value},
+    combinator::{eof, map, opt, value},
     error::VerboseError,
     multi::separated_list1,
-    sequence::{pair, separated_pair},
+    sequence::{pair, preceded, separated_pair},
     Parser,
 };
-use std::{borrow::Borrow, fmt, str::FromStr};
+use std::{
+    borrow::Borrow,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
 use tracing::trace;
 
 use nom::{
@@ -22,42 +28,96 @@ use nom_supreme::{
 };
 
 use crate::{
+    genomics::interval::OneBased,
     gff::{
-        parsers::{strand, ParseError},
+        parsers::{range_bound, start_bound, strand, ParseError},
         Strand,
     },
     NomResult,
 };
 
-use super::{GffError, UnescapedString};
+use super::{is_attribute_char, is_list_char, Escaped, GffError, UnescapedString};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct AttributeSet {
     pub id: Option<Id>,
     pub name: Option<UnescapedString>,
-    pub alias: Option<UnescapedString>,
+    pub alias: Option<Vec<UnescapedString>>,
     pub parent: Option<Vec<Id>>,
     pub target: Option<TargetAttr>,
     pub gap: Option<Vec<(GapKind, usize)>>,
     pub derives_from: Option<Id>,
-    pub note: Option<UnescapedString>,
-    pub dbx_ref: Option<UnescapedString>,
-    pub ontology_term: Option<UnescapedString>,
+    pub note: Option<Vec<UnescapedString>>,
+    pub dbx_ref: Option<Vec<DbXref>>,
+    pub ontology_term: Option<Vec<DbXref>>,
     pub is_circular: Option<bool>,
-    pub other: Option<Vec<(Box<str>, UnescapedString)>>,
+    /// Attributes without a reserved meaning, each with its comma separated values
+    pub other: Option<Vec<(Box<str>, Vec<UnescapedString>)>>,
+    /// The keys in the order they were parsed, which they are written back out in.
+    /// Any attributes whose keys are missing are written after these, in the order of the fields above
+    pub(super) order: KeyOrder,
+}
+
+/// The order of the keys of an [`AttributeSet`], which only affects how it is written,
+/// so two sets with the same attributes in a different order are still equal and hash the same
+#[derive(Debug, Default, Clone)]
+pub(super) struct KeyOrder(Vec<Box<str>>);
+
+impl PartialEq for KeyOrder {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for KeyOrder {}
+
+impl Hash for KeyOrder {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl Deref for KeyOrder {
+    type Target = Vec<Box<str>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for KeyOrder {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TargetAttr {
     target_id: Id,
-    start: usize,
-    end: usize,
+    range: OneBased,
     strand: Option<Strand>,
 }
 
 impl AttributeSet {
+    /// The reserved attributes, in the order they are written when not otherwise ordered
+    const RESERVED: [&'static str; 11] = [
+        "ID",
+        "Name",
+        "Alias",
+        "Parent",
+        "Target",
+        "Gap",
+        "Derives_from",
+        "Note",
+        "Dbxref",
+        "Ontology_term",
+        "Is_circular",
+    ];
+
     #[tracing::instrument]
     pub(crate) fn parse(src: &str) -> Result<Self, GffError> {
+        // An empty column 9
+        if src == "." {
+            return Ok(Self::default());
+        }
         let attrs =
             final_parser::<_, _, VerboseError<&str>, ParseError>(parse_separated_terminated_res(
                 Self::get_key_val,
@@ -65,10 +125,11 @@ impl AttributeSet {
                 eof,
                 AttributeSet::default,
                 |mut attrs, (key, val)| {
+                    attrs.order.push(key.into());
                     match key {
                         "ID" => attrs.id = Some(Id::new(val)),
                         "Name" => attrs.name = Some(UnescapedString::new(val)?),
-                        "Alias" => attrs.alias = Some(UnescapedString::new(val)?),
+                        "Alias" => attrs.alias = Some(parse_list(val)?),
                         "Parent" => {
                             attrs.parent =
                                 Some(final_parser::<_, _, VerboseError<&str>, ParseError>(
@@ -93,9 +154,9 @@ impl AttributeSet {
                             )(val)?)
                         }
                         "Derives_from" => attrs.derives_from = Some(Id::new(val)),
-                        "Note" => attrs.note = Some(UnescapedString::new(val)?),
-                        "Dbxref" => attrs.dbx_ref = Some(UnescapedString::new(val)?),
-                        "Ontology_term" => attrs.ontology_term = Some(UnescapedString::new(val)?),
+                        "Note" => attrs.note = Some(parse_list(val)?),
+                        "Dbxref" => attrs.dbx_ref = Some(DbXref::parse_list(val)?),
+                        "Ontology_term" => attrs.ontology_term = Some(DbXref::parse_list(val)?),
                         "Is_circular" => {
                             attrs.is_circular =
                                 final_parser::<_, _, VerboseError<&str>, ParseError>(map(
@@ -114,7 +175,7 @@ impl AttributeSet {
                         tag => attrs
                             .other
                             .get_or_insert(Vec::new())
-                            .push((tag.into(), UnescapedString::new(val)?)),
+                            .push((tag.into(), parse_list(val)?)),
                     };
                     Ok(attrs)
                 },
@@ -123,6 +184,34 @@ impl AttributeSet {
         Ok(attrs)
     }
 
+    /// The values of the attribute without a reserved meaning named `key`
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&[UnescapedString]> {
+        self.other
+            .iter()
+            .flatten()
+            .find(|(other, _)| &**other == key)
+            .map(|(_, vals)| vals.as_slice())
+    }
+
+    /// The ID of the first `Dbxref` to the database `db`, such as the `GeneID` of a feature
+    #[must_use]
+    pub fn dbxref(&self, db: &str) -> Option<&str> {
+        self.dbx_ref
+            .iter()
+            .flatten()
+            .find(|dbxref| &*dbxref.db == db)
+            .map(|dbxref| &*dbxref.id)
+    }
+
+    /// Returns `true` if `term`, such as `GO:0046703`, is one of the `Ontology_term`s
+    #[must_use]
+    pub fn has_ontology_term(&self, term: &str) -> bool {
+        self.ontology_term.iter().flatten().any(|ontology_term| {
+            term.split_once(':') == Some((&ontology_term.db, &ontology_term.id))
+        })
+    }
+
     #[tracing::instrument]
     fn get_key_val<'source, 'ret>(chunk: &'source str) -> NomResult<'ret, (&'ret str, &'ret str)>
     where
@@ -134,72 +223,128 @@ impl AttributeSet {
         trace!("Returning ({}, {}), Remaining: \"{ret}\"", this.0, this.1);
         Ok((ret, this))
     }
+
+    /// Writes the value of a reserved attribute, if it is set
+    fn reserved_value(&self, key: &str) -> Option<String> {
+        let escaped = |val: &UnescapedString| Escaped(val, is_attribute_char).to_string();
+        match key {
+            "ID" => self.id.as_ref().map(ToString::to_string),
+            "Name" => self.name.as_ref().map(escaped),
+            "Alias" => self.alias.as_deref().map(write_list),
+            "Parent" => self.parent.as_ref().map(|parents| parents.join(",")),
+            "Target" => self.target.as_ref().map(ToString::to_string),
+            "Gap" => self.gap.as_ref().map(|gaps| {
+                gaps.iter()
+                    .map(|(kind, len)| format!("{kind}{len}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }),
+            "Derives_from" => self.derives_from.as_ref().map(ToString::to_string),
+            "Note" => self.note.as_deref().map(write_list),
+            "Dbxref" => self.dbx_ref.as_deref().map(join),
+            "Ontology_term" => self.ontology_term.as_deref().map(join),
+            "Is_circular" => self.is_circular.map(|circular| circular.to_string()),
+            _ => None,
+        }
+    }
 }
 
-macro_rules! opt_field {
-    ($fmt:expr, $self:ident, $id:ident) => {
-        if let Some(ref $id) = $self.$id {
-            writeln!($fmt, "\t{}: {}", stringify!($id), $id)?;
-        };
-    };
+/// Decodes the comma separated values of an attribute
+fn parse_list(src: &str) -> Result<Vec<UnescapedString>, GffError> {
+    src.split(',').map(UnescapedString::new).collect()
 }
 
+/// Joins the values of an attribute with commas
+fn join<T: fmt::Display>(vals: &[T]) -> String {
+    vals.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn write_list(vals: &[UnescapedString]) -> String {
+    join(
+        &vals
+            .iter()
+            .map(|val| Escaped(val, is_list_char))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Writes the attributes as GFF3 column 9, in the order given by [`AttributeSet::order`]
 impl fmt::Display for AttributeSet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "AttributeSet {{")?;
-        opt_field!(f, self, id);
-        opt_field!(f, self, name);
-        opt_field!(f, self, alias);
-        if let Some(ref parents) = self.parent {
-            let parentstr = parents.join(";");
-            if parents.len() == 1 {
-                writeln!(f, "\tParent: {}", parentstr)?;
-            } else {
-                writeln!(f, "\tParents: {}", parentstr)?;
+        let others = self.other.as_deref().unwrap_or_default();
+        let mut reserved_written = [false; Self::RESERVED.len()];
+        let mut others_written = vec![false; others.len()];
+        let mut attrs = Vec::new();
+        let mut push = |key: &str| {
+            if let Some(i) = Self::RESERVED.iter().position(|&reserved| reserved == key) {
+                if !std::mem::replace(&mut reserved_written[i], true) {
+                    if let Some(val) = self.reserved_value(key) {
+                        attrs.push(format!("{key}={val}"));
+                    }
+                }
+            } else if let Some(i) =
+                (0..others.len()).find(|&i| !others_written[i] && &*others[i].0 == key)
+            {
+                others_written[i] = true;
+                attrs.push(format!("{key}={}", write_list(&others[i].1)));
             }
         };
-        opt_field!(f, self, target);
-        if let Some(ref gaps) = self.gap {
-            if gaps.len() == 1 {
-                let (kind, len) = gaps.get(0).expect("Length is 1");
-                writeln!(f, "\tGap: {}{}", kind, len)?;
-            } else {
-                let gapstr = gaps
-                    .iter()
-                    .map(|(kind, len)| format!("{kind}{len}"))
-                    .reduce(|prev, new| format!("{prev} {new}"))
-                    .expect("self.gap is only ever Some if it's len is at least one");
-                writeln!(f, "\tGaps: {}", gapstr)?;
-            }
-        };
-        opt_field!(f, self, derives_from);
-        opt_field!(f, self, note);
-        opt_field!(f, self, dbx_ref);
-        opt_field!(f, self, ontology_term);
-        if let Some(circular) = self.is_circular {
-            writeln!(f, "is_circular: {circular}")?;
+        self.order.iter().for_each(|key| push(key));
+        Self::RESERVED.iter().for_each(|key| push(key));
+        others.iter().for_each(|(key, _)| push(key));
+        if attrs.is_empty() {
+            f.write_str(".")
+        } else {
+            f.write_str(&attrs.join(";"))
         }
-        if let Some(ref others) = self.other {
-            for (key, val) in others {
-                writeln!(f, "\t{}: {}", key, val)?;
-            }
-        }
-        write!(f, "}}")
     }
 }
 
 impl TargetAttr {
+    /// Creates the target of an alignment, such as for building an [`Alignment`](super::alignment::Alignment)
+    /// of a feature that was not read from a file
+    #[must_use]
+    pub fn new(target_id: &str, range: OneBased, strand: Option<Strand>) -> Self {
+        Self {
+            target_id: Id::new(target_id),
+            range,
+            strand,
+        }
+    }
+
+    /// The ID of the sequence aligned to
+    #[must_use]
+    pub fn target_id(&self) -> &Id {
+        &self.target_id
+    }
+
+    /// The aligned positions of the target
+    #[must_use]
+    pub fn range(&self) -> OneBased {
+        self.range
+    }
+
+    /// The strand of the target that was aligned to, if given
+    #[must_use]
+    pub fn strand(&self) -> Option<&Strand> {
+        self.strand.as_ref()
+    }
+
     fn parse(src: &str) -> NomResult<'_, Self> {
         tuple((
             map(terminated(is_not(" "), tag(" ")), Id::new),
-            map_res(terminated(digit1, tag(" ")), FromStr::from_str),
-            map_res(terminated(digit1, tag(" ")), FromStr::from_str),
-            map_res(strand, |s| s.map(Strand::parse).transpose()),
+            terminated(start_bound, tag(" ")),
+            range_bound,
+            map_res(opt(preceded(tag(" "), strand)), |s| {
+                s.flatten().map(Strand::parse).transpose()
+            }),
         ))
         .map(|(target_id, start, end, strand)| Self {
             target_id,
-            start,
-            end,
+            range: OneBased::new(start, end),
             strand,
         })
         .parse(src)
@@ -208,7 +353,49 @@ impl TargetAttr {
 
 impl fmt::Display for TargetAttr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:#?}", self)
+        write!(
+            f,
+            "{} {} {}",
+            self.target_id, self.range.start, self.range.end
+        )?;
+        if let Some(ref strand) = self.strand {
+            write!(f, " {strand}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A reference to an entry in another database, as found in `Dbxref` and `Ontology_term`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DbXref {
+    /// The database, such as `GeneID` or `GO`
+    pub db: UnescapedString,
+    /// The ID within the database
+    pub id: UnescapedString,
+}
+
+impl DbXref {
+    fn parse_list(src: &str) -> Result<Vec<Self>, GffError> {
+        src.split(',')
+            .map(|dbxref| {
+                let (db, id) = dbxref.split_once(':').ok_or(GffError::MalformedDbXref)?;
+                Ok(Self {
+                    db: UnescapedString::new(db)?,
+                    id: UnescapedString::new(id)?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for DbXref {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            Escaped(&self.db, |c| is_list_char(c) && c != ':'),
+            Escaped(&self.id, is_list_char)
+        )
     }
 }
 
@@ -228,7 +415,7 @@ impl Borrow<str> for Id {
 }
 
 impl Id {
-    fn new<T: ToString>(src: T) -> Self {
+    pub(crate) fn new<T: ToString>(src: T) -> Self {
         Self(src.to_string().into_boxed_str())
     }
 }
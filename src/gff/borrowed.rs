@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+
+use nom::error::VerboseError;
+use nom_supreme::final_parser::final_parser;
+
+use super::{
+    attr::AttributeSet,
+    parsers::{self, ParseError},
+    Entry, GffError, Strand, UnescapedString,
+};
+use crate::genomics::interval::OneBased;
+
+/// An [`Entry`] that borrows its text from the line it was parsed from, only allocating
+/// for the columns that contain percent-escapes.
+///
+/// Column 9 is not parsed until its attributes are read or the entry is made owned,
+/// so filtering a file by sequence, type or position does not allocate at all
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryRef<'a> {
+    pub seq_id: Cow<'a, str>,
+    pub source: Cow<'a, str>,
+    pub feature_type: Cow<'a, str>,
+    pub range: OneBased,
+    pub score: Option<f64>,
+    pub strand: Option<Strand>,
+    pub phase: Option<u8>,
+    pub attrs: AttributeSetRef<'a>,
+}
+
+impl<'a> EntryRef<'a> {
+    /// Parses a single entry line, without its line ending
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of the first eight columns are malformed
+    #[tracing::instrument(level = "trace")]
+    pub fn parse(line: &'a str) -> Result<Self, GffError> {
+        let (seq_id, source, feature_type, start, end, score, strand, phase, attrs) =
+            final_parser::<_, _, VerboseError<&str>, ParseError>(parsers::entry)(line)?;
+        Ok(Self {
+            seq_id: UnescapedString::decode(seq_id)?,
+            source: UnescapedString::decode(source)?,
+            feature_type: UnescapedString::decode(feature_type)?,
+            range: OneBased::new(start, end),
+            score,
+            strand: strand.map(Strand::parse).transpose()?,
+            phase,
+            attrs: AttributeSetRef::new(attrs),
+        })
+    }
+
+    /// Copies this entry into an owned [`Entry`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the attributes are malformed
+    pub fn try_to_owned(&self) -> Result<Entry, GffError> {
+        Ok(Entry {
+            seq_id: UnescapedString(self.seq_id.clone().into()),
+            source: UnescapedString(self.source.clone().into()),
+            feature_type: UnescapedString(self.feature_type.clone().into()),
+            range: self.range,
+            score: self.score,
+            strand: self.strand.clone(),
+            phase: self.phase,
+            attrs: self.attrs.try_to_owned()?,
+            line: None,
+        })
+    }
+}
+
+/// The attributes of an [`EntryRef`], read from column 9 as they are asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttributeSetRef<'a> {
+    src: &'a str,
+}
+
+impl<'a> AttributeSetRef<'a> {
+    /// Wraps column 9 of an entry, which may be `.` if it has no attributes
+    #[must_use]
+    pub fn new(src: &'a str) -> Self {
+        Self {
+            src: if src == "." { "" } else { src },
+        }
+    }
+
+    /// Every attribute as a key and its value, still percent-escaped
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.src
+            .split(';')
+            .filter(|attr| !attr.is_empty())
+            .map(|attr| attr.split_once('=').unwrap_or((attr, "")))
+    }
+
+    /// The value of the first attribute named `key`, still percent-escaped
+    #[must_use]
+    pub fn raw(&self, key: &str) -> Option<&'a str> {
+        self.iter()
+            .find(|&(other, _)| other == key)
+            .map(|(_, val)| val)
+    }
+
+    /// The decoded value of the first attribute named `key`
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the value contains a malformed escape
+    pub fn get(&self, key: &str) -> Result<Option<Cow<'a, str>>, GffError> {
+        self.raw(key).map(UnescapedString::decode).transpose()
+    }
+
+    /// The `ID` attribute, which like [`AttributeSet::id`] is not decoded
+    #[must_use]
+    pub fn id(&self) -> Option<&'a str> {
+        self.raw("ID")
+    }
+
+    /// The IDs listed in the `Parent` attribute
+    pub fn parents(&self) -> impl Iterator<Item = &'a str> {
+        self.raw("Parent")
+            .into_iter()
+            .flat_map(|parents| parents.split(','))
+    }
+
+    /// Parses every attribute into an owned [`AttributeSet`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any attribute is malformed
+    pub fn try_to_owned(&self) -> Result<AttributeSet, GffError> {
+        if self.src.is_empty() {
+            return Ok(AttributeSet::default());
+        }
+        AttributeSet::parse(self.src)
+    }
+}
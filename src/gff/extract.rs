@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use super::{
+    graph::{Feature, FeatureGraph},
+    Entry, GffError, Strand, GFF,
+};
+use crate::{
+    fasta::Fasta,
+    genomics::{genome::DnaSeq, nucleotide::DNA},
+    proteomics::{amino::AminoAcid, Proteome},
+};
+
+/// The sequences of every transcript of a [`GFF`], as produced by [`GFF::extract_sequences`].
+///
+/// Each record is described by the ID of its transcript
+#[derive(Debug, Clone, Default)]
+pub struct ExtractedSequences {
+    /// The spliced exons of each transcript
+    pub transcripts: Vec<Fasta<DnaSeq>>,
+    /// The spliced coding sequence of each transcript with CDS, starting at its first whole codon
+    pub cds: Vec<Fasta<DnaSeq>>,
+    /// The translation of each coding sequence
+    pub proteins: Vec<Fasta<Proteome>>,
+}
+
+impl GFF {
+    /// Extracts the transcript, coding and protein sequence of every transcript from `genome`,
+    /// whose records are named by the sequence IDs of the entries.
+    ///
+    /// A transcript is any feature with `exon` or `CDS` children, and its exons default to its CDS
+    /// if it has none. Features on the [`Strand::Negative`] strand are reverse complemented, and the
+    /// `phase` of the first CDS segment is skipped before translating.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the features do not form a valid [`FeatureGraph`],
+    /// if a feature lies outside of its sequence or on a sequence missing from `genome`,
+    /// or if a coding sequence contains an ambiguous codon
+    #[tracing::instrument(skip_all)]
+    pub fn extract_sequences(
+        &self,
+        genome: &[Fasta<DnaSeq>],
+    ) -> Result<ExtractedSequences, GffError> {
+        let genome = genome
+            .iter()
+            .filter_map(|record| {
+                let name = record.description.as_deref()?.split_whitespace().next()?;
+                Some((name, &record.sequence))
+            })
+            .collect::<HashMap<_, _>>();
+        let graph = FeatureGraph::new(self)?;
+        let mut extracted = ExtractedSequences::default();
+        for transcript in graph.features() {
+            let segments = |feature_type: &str| {
+                let mut segments = graph
+                    .children(transcript)
+                    .filter(|child| child.feature_type() == feature_type)
+                    .flat_map(Feature::entries)
+                    .copied()
+                    .collect::<Vec<_>>();
+                segments.sort_unstable_by_key(|entry| entry.range.start);
+                segments
+            };
+            let (exons, cds) = (segments("exon"), segments("CDS"));
+            if exons.is_empty() && cds.is_empty() {
+                continue;
+            }
+            let id = transcript.id().unwrap_or_else(|| transcript.feature_type());
+            let is_negative = transcript.entries()[0].strand == Some(Strand::Negative);
+            let exons = if exons.is_empty() { &cds } else { &exons };
+            extracted.transcripts.push(Fasta {
+                description: Some(id.into()),
+                sequence: splice(&genome, exons, is_negative)?,
+            });
+            if cds.is_empty() {
+                continue;
+            }
+
+            let first = if is_negative { cds.last() } else { cds.first() };
+            let phase = first.and_then(|entry| entry.phase).unwrap_or(0).into();
+            let coding = splice(&genome, &cds, is_negative)?;
+            let coding = coding.iter().skip(phase).copied().collect::<DnaSeq>();
+            extracted.proteins.push(Fasta {
+                description: Some(id.into()),
+                sequence: translate(id, &coding)?,
+            });
+            extracted.cds.push(Fasta {
+                description: Some(id.into()),
+                sequence: coding,
+            });
+        }
+        Ok(extracted)
+    }
+}
+
+/// Joins the sequences of `segments` (sorted by start) in order of transcription
+fn splice(
+    genome: &HashMap<&str, &DnaSeq>,
+    segments: &[&Entry],
+    is_negative: bool,
+) -> Result<DnaSeq, GffError> {
+    let mut spliced = Vec::new();
+    for entry in segments {
+        let seq = genome
+            .get(&*entry.seq_id)
+            .ok_or_else(|| GffError::MissingSequence {
+                seq_id: (*entry.seq_id).into(),
+            })?;
+        let bases = seq
+            .slice(entry.range)
+            .ok_or_else(|| GffError::FeatureOutOfBounds {
+                seq_id: (*entry.seq_id).into(),
+                end: entry.range.end,
+            })?;
+        spliced.extend_from_slice(bases);
+    }
+    Ok(if is_negative {
+        spliced.into_iter().rev().map(DNA::complement).collect()
+    } else {
+        spliced.into_iter().collect()
+    })
+}
+
+/// Translates every whole codon of `coding`
+fn translate(id: &str, coding: &DnaSeq) -> Result<Proteome, GffError> {
+    coding
+        .as_slice()
+        .chunks_exact(3)
+        .map(|codon| {
+            let codon = codon.iter().map(char::from).collect::<String>();
+            AminoAcid::translate_dna(&codon).map_err(|_| GffError::Untranslatable {
+                id: id.into(),
+                codon: codon.into(),
+            })
+        })
+        .collect()
+}
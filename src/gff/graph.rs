@@ -0,0 +1,235 @@
+use std::{borrow::Borrow, collections::HashMap};
+
+use super::{Entry, GffError, GFF};
+
+/// A feature of a [`FeatureGraph`]: either every entry sharing an ID, such as a CDS split across
+/// several lines, or a single entry without an ID
+#[derive(Debug, Clone)]
+pub struct Feature<'a> {
+    index: usize,
+    id: Option<&'a str>,
+    entries: Vec<&'a Entry>,
+    parents: Vec<usize>,
+    children: Vec<usize>,
+}
+
+impl<'a> Feature<'a> {
+    /// The ID shared by the entries of this feature
+    #[must_use]
+    pub fn id(&self) -> Option<&'a str> {
+        self.id
+    }
+
+    /// The entries that make up this feature, in file order
+    #[must_use]
+    pub fn entries(&self) -> &[&'a Entry] {
+        &self.entries
+    }
+
+    /// The type of the first entry of this feature
+    #[must_use]
+    pub fn feature_type(&self) -> &'a str {
+        &self.entries[0].feature_type
+    }
+
+    /// Returns `true` if this feature has no parents
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// A name for this feature in error messages: its ID, or else the location of its entry
+    fn describe(&self) -> Box<str> {
+        self.id.map_or_else(
+            || {
+                let entry = self.entries[0];
+                format!(
+                    "{} at {}:{}..{}",
+                    &*entry.feature_type, &*entry.seq_id, entry.range.start, entry.range.end
+                )
+                .into()
+            },
+            Into::into,
+        )
+    }
+}
+
+/// The part-of hierarchy of the features of a [`GFF`], linked through their `ID` and `Parent` attributes
+#[derive(Debug, Clone)]
+pub struct FeatureGraph<'a> {
+    features: Vec<Feature<'a>>,
+    by_id: HashMap<&'a str, usize>,
+}
+
+impl<'a> FeatureGraph<'a> {
+    /// Links the entries of `gff` into a graph.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if an entry names a `Parent` that no entry has as its `ID`,
+    /// or if a feature is its own ancestor
+    #[tracing::instrument(skip_all)]
+    pub fn new(gff: &'a GFF) -> Result<Self, GffError> {
+        let mut features: Vec<Feature<'a>> = Vec::new();
+        let mut by_id = HashMap::new();
+        for entry in &gff.entries {
+            let id = entry.attrs.id.as_ref().map(Borrow::<str>::borrow);
+            let index = match id.and_then(|id| by_id.get(id)) {
+                Some(&index) => index,
+                None => {
+                    let index = features.len();
+                    if let Some(id) = id {
+                        by_id.insert(id, index);
+                    }
+                    features.push(Feature {
+                        index,
+                        id,
+                        entries: Vec::new(),
+                        parents: Vec::new(),
+                        children: Vec::new(),
+                    });
+                    index
+                }
+            };
+            features[index].entries.push(entry);
+        }
+
+        for index in 0..features.len() {
+            let parents = features[index]
+                .entries
+                .iter()
+                .flat_map(|entry| entry.attrs.parent.iter().flatten())
+                .map(Borrow::<str>::borrow);
+            let mut resolved = Vec::new();
+            for parent in parents {
+                let &parent_index = by_id.get(parent).ok_or_else(|| GffError::DanglingParent {
+                    id: features[index].describe(),
+                    parent: parent.into(),
+                })?;
+                if !resolved.contains(&parent_index) {
+                    resolved.push(parent_index);
+                }
+            }
+            for &parent in &resolved {
+                features[parent].children.push(index);
+            }
+            features[index].parents = resolved;
+        }
+
+        let graph = Self { features, by_id };
+        graph.check_acyclic()?;
+        Ok(graph)
+    }
+
+    /// Walks down from every feature, failing if a walk reaches a feature it is still below
+    fn check_acyclic(&self) -> Result<(), GffError> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        let mut states = vec![State::Unvisited; self.features.len()];
+        for start in 0..self.features.len() {
+            if states[start] != State::Unvisited {
+                continue;
+            }
+            // Each frame is a feature and the number of its children visited so far
+            let mut stack = vec![(start, 0)];
+            states[start] = State::InProgress;
+            while let Some((index, next)) = stack.last_mut() {
+                let feature = &self.features[*index];
+                match feature.children.get(*next) {
+                    Some(&child) => {
+                        *next += 1;
+                        match states[child] {
+                            State::Unvisited => {
+                                states[child] = State::InProgress;
+                                stack.push((child, 0));
+                            }
+                            State::InProgress => {
+                                return Err(GffError::FeatureCycle {
+                                    id: self.features[child].describe(),
+                                })
+                            }
+                            State::Done => {}
+                        }
+                    }
+                    None => {
+                        states[*index] = State::Done;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every feature, in the order they first appear in the file
+    #[must_use]
+    pub fn features(&self) -> &[Feature<'a>] {
+        &self.features
+    }
+
+    /// Returns the feature with the given `ID`
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&Feature<'a>> {
+        self.by_id.get(id).map(|&index| &self.features[index])
+    }
+
+    /// The features without parents, such as genes
+    pub fn roots(&self) -> impl Iterator<Item = &Feature<'a>> {
+        self.features.iter().filter(|feature| feature.is_root())
+    }
+
+    /// The direct parents of `feature`
+    pub fn parents<'g>(&'g self, feature: &Feature<'a>) -> impl Iterator<Item = &'g Feature<'a>> {
+        let parents = self.features[feature.index].parents.iter();
+        parents.map(|&index| &self.features[index])
+    }
+
+    /// The direct children of `feature`
+    pub fn children<'g>(&'g self, feature: &Feature<'a>) -> impl Iterator<Item = &'g Feature<'a>> {
+        let children = self.features[feature.index].children.iter();
+        children.map(|&index| &self.features[index])
+    }
+
+    /// Every feature above `feature`, such as the transcript and gene of an exon, in depth-first order
+    /// and each listed once even if reachable through several parents
+    #[must_use]
+    pub fn ancestors(&self, feature: &Feature<'a>) -> Vec<&Feature<'a>> {
+        self.walk(feature, |feature| &feature.parents)
+    }
+
+    /// Every feature below `feature`, such as the transcripts, exons and CDS of a gene, in depth-first order
+    /// and each listed once even if reachable through several parents
+    #[must_use]
+    pub fn descendants(&self, feature: &Feature<'a>) -> Vec<&Feature<'a>> {
+        self.walk(feature, |feature| &feature.children)
+    }
+
+    fn walk<'g>(
+        &'g self,
+        feature: &Feature<'a>,
+        next: impl Fn(&'g Feature<'a>) -> &'g [usize],
+    ) -> Vec<&'g Feature<'a>> {
+        let mut seen = vec![false; self.features.len()];
+        seen[feature.index] = true;
+        let mut stack = next(&self.features[feature.index])
+            .iter()
+            .rev()
+            .copied()
+            .collect::<Vec<_>>();
+        let mut found = Vec::new();
+        while let Some(index) = stack.pop() {
+            if std::mem::replace(&mut seen[index], true) {
+                continue;
+            }
+            let feature = &self.features[index];
+            found.push(feature);
+            stack.extend(next(feature).iter().rev());
+        }
+        found
+    }
+}
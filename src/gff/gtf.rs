@@ -0,0 +1,443 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+use nom::{error::VerboseError, Parser};
+use nom_supreme::final_parser::final_parser;
+
+use super::{
+    attr::{AttributeSet, Id},
+    graph::{Feature, FeatureGraph},
+    parsers::{self, ParseError},
+    Entry, GffError, Strand, UnescapedString, GFF,
+};
+use crate::genomics::interval::OneBased;
+
+/// Reads the entries of a GTF (GFF2) file line by line.
+///
+/// The `key "value";` attributes of column 9 are kept, in order, in [`AttributeSet::other`],
+/// and no `ID` or `Parent` links are made; see [`GFF::from_gtf`] for that
+#[derive(Debug)]
+pub struct GtfReader<R> {
+    src: R,
+    buf: String,
+    line: usize,
+}
+
+impl<R: BufRead> GtfReader<R> {
+    pub fn new(src: R) -> Self {
+        Self {
+            src,
+            buf: String::new(),
+            line: 0,
+        }
+    }
+
+    /// Reads the next entry along with its (1-based) line number, or returns `None` at the end of the input
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from the source fails or the next line is malformed,
+    /// as a [`GffError::AtLine`] holding the line number
+    pub fn read_entry(&mut self) -> Result<Option<(usize, Entry)>, GffError> {
+        loop {
+            self.buf.clear();
+            if self.src.read_line(&mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let line = self.buf.trim_end_matches(['\r', '\n']);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            return parse_entry(line)
+                .map(|mut entry| {
+                    entry.line = Some(self.line);
+                    Some((self.line, entry))
+                })
+                .map_err(|source| GffError::AtLine {
+                    line: self.line,
+                    source: Box::new(source),
+                });
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for GtfReader<R> {
+    type Item = Result<(usize, Entry), GffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+fn column<'a, T>(
+    parser: impl Parser<&'a str, T, VerboseError<&'a str>>,
+    src: &'a str,
+) -> Result<T, GffError> {
+    Ok(final_parser::<_, _, VerboseError<&str>, ParseError>(
+        parser,
+    )(src)?)
+}
+
+fn parse_entry(line: &str) -> Result<Entry, GffError> {
+    let columns = line.splitn(9, '\t').collect::<Vec<_>>();
+    let [seq_id, source, feature_type, start, end, score, strand, phase, attrs] = columns[..]
+    else {
+        return Err(GffError::MalformedLine);
+    };
+    Ok(Entry {
+        seq_id: UnescapedString(seq_id.into()),
+        source: UnescapedString(source.into()),
+        feature_type: UnescapedString(feature_type.into()),
+        range: OneBased::new(
+            column(parsers::start_bound, start)?,
+            column(parsers::range_bound, end)?,
+        ),
+        score: column(parsers::score, score)?,
+        strand: column(parsers::strand, strand)?
+            .map(Strand::parse)
+            .transpose()?,
+        phase: column(parsers::phase, phase)?,
+        attrs: parse_attributes(attrs)?,
+        line: None,
+    })
+}
+
+/// Parses GTF column 9, `key "value"; key value;`
+fn parse_attributes(src: &str) -> Result<AttributeSet, GffError> {
+    let mut attrs = AttributeSet::default();
+    for pair in split_attributes(src)?
+        .into_iter()
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+    {
+        let (key, val) = pair
+            .split_once(char::is_whitespace)
+            .ok_or(GffError::InvalidAttribute)?;
+        let val = val.trim();
+        let val = val
+            .strip_prefix('"')
+            .and_then(|val| val.strip_suffix('"'))
+            .unwrap_or(val);
+        attrs.order.push(key.into());
+        attrs
+            .other
+            .get_or_insert(Vec::new())
+            .push((key.into(), vec![UnescapedString(val.into())]));
+    }
+    Ok(attrs)
+}
+
+/// Splits GTF column 9 on the `;` that are outside of quoted values
+fn split_attributes(src: &str) -> Result<Vec<&str>, GffError> {
+    let (mut pairs, mut start, mut quoted) = (Vec::new(), 0, false);
+    for (i, c) in src.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                pairs.push(&src[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(GffError::InvalidAttribute);
+    }
+    pairs.push(&src[start..]);
+    Ok(pairs)
+}
+
+/// Returns the value of the GTF attribute named `key`
+fn gtf_attr<'a>(entry: &'a Entry, key: &str) -> Option<&'a str> {
+    entry.attrs.get(key)?.first().map(|val| &**val)
+}
+
+/// Links an entry to its GFF3 `ID` and `Parent`, which are written before its GTF attributes
+fn link(mut entry: Entry, id: Option<&str>, parent: Option<&str>) -> Entry {
+    let mut keys = Vec::new();
+    if let Some(id) = id {
+        entry.attrs.id = Some(Id::new(id));
+        keys.push("ID".into());
+    }
+    if let Some(parent) = parent {
+        entry.attrs.parent = Some(vec![Id::new(parent)]);
+        keys.push("Parent".into());
+    }
+    entry.attrs.order.splice(0..0, keys);
+    entry
+}
+
+/// Creates a `feature_type` entry spanning `children`, keeping the GTF attributes named by `keep`
+fn synthesize<'a>(
+    feature_type: &str,
+    mut children: impl Iterator<Item = &'a Entry>,
+    keep: fn(&str) -> bool,
+) -> Entry {
+    let first = children
+        .next()
+        .expect("Genes and transcripts are only made for their features");
+    let mut attrs = AttributeSet::default();
+    for (key, val) in first.attrs.other.iter().flatten() {
+        if keep(key) {
+            attrs.order.push(key.clone());
+            attrs
+                .other
+                .get_or_insert(Vec::new())
+                .push((key.clone(), val.clone()));
+        }
+    }
+    let mut entry = Entry {
+        seq_id: first.seq_id.clone(),
+        source: first.source.clone(),
+        feature_type: UnescapedString(feature_type.into()),
+        range: first.range,
+        score: None,
+        strand: first.strand.clone(),
+        phase: None,
+        attrs,
+        line: None,
+    };
+    for child in children {
+        entry.range.start = entry.range.start.min(child.range.start);
+        entry.range.end = entry.range.end.max(child.range.end);
+    }
+    entry
+}
+
+#[derive(Default)]
+struct Transcript {
+    id: Box<str>,
+    transcript: Option<Entry>,
+    features: Vec<Entry>,
+}
+
+#[derive(Default)]
+struct Gene {
+    id: Box<str>,
+    gene: Option<Entry>,
+    /// Features that belong to the gene but to none of its transcripts
+    features: Vec<Entry>,
+    transcripts: Vec<Transcript>,
+    by_transcript: HashMap<Box<str>, usize>,
+}
+
+impl GFF {
+    /// Reads a GTF file and converts it to GFF3 with [`GFF::from_gtf`]
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading fails or any line is malformed
+    #[tracing::instrument(skip_all)]
+    pub fn read_gtf(src: &mut impl Read) -> Result<Self, GffError> {
+        let entries = GtfReader::new(BufReader::new(src))
+            .map(|entry| entry.map(|(_, entry)| entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_gtf(entries))
+    }
+
+    /// Builds the GFF3 hierarchy implied by the `gene_id` and `transcript_id` attributes of GTF entries.
+    ///
+    /// `gene` and `transcript` entries are given their `gene_id` and `transcript_id` as their `ID`,
+    /// and are created spanning their features when the GTF has none. Every other entry gets its
+    /// transcript (or gene, if it has no `transcript_id`) as its `Parent`. The entries are grouped by gene,
+    /// in the order genes first appear, and entries without a `gene_id` are left unlinked at the end
+    #[must_use]
+    pub fn from_gtf(entries: impl IntoIterator<Item = Entry>) -> Self {
+        let mut genes: Vec<Gene> = Vec::new();
+        let mut by_gene = HashMap::new();
+        let mut unlinked = Vec::new();
+        for entry in entries {
+            let Some(gene_id) = gtf_attr(&entry, "gene_id") else {
+                unlinked.push(entry);
+                continue;
+            };
+            let gene_index = *by_gene.entry(Box::<str>::from(gene_id)).or_insert_with(|| {
+                genes.push(Gene {
+                    id: gene_id.into(),
+                    ..Gene::default()
+                });
+                genes.len() - 1
+            });
+            let gene = &mut genes[gene_index];
+            if &*entry.feature_type == "gene" {
+                gene.gene = Some(entry);
+                continue;
+            }
+            let Some(transcript_id) = gtf_attr(&entry, "transcript_id") else {
+                gene.features.push(entry);
+                continue;
+            };
+            let transcript_index = *gene
+                .by_transcript
+                .entry(transcript_id.into())
+                .or_insert_with(|| {
+                    gene.transcripts.push(Transcript {
+                        id: transcript_id.into(),
+                        ..Transcript::default()
+                    });
+                    gene.transcripts.len() - 1
+                });
+            let transcript = &mut gene.transcripts[transcript_index];
+            if &*entry.feature_type == "transcript" {
+                transcript.transcript = Some(entry);
+            } else {
+                transcript.features.push(entry);
+            }
+        }
+
+        let mut gff = GFF::default();
+        for mut gene in genes {
+            for transcript in &mut gene.transcripts {
+                if transcript.transcript.is_none() {
+                    transcript.transcript = Some(synthesize(
+                        "transcript",
+                        transcript.features.iter(),
+                        |key| key.starts_with("gene_") || key.starts_with("transcript_"),
+                    ));
+                }
+            }
+            let gene_entry = gene.gene.take().unwrap_or_else(|| {
+                let children = gene
+                    .transcripts
+                    .iter()
+                    .flat_map(|transcript| transcript.transcript.iter())
+                    .chain(&gene.features);
+                synthesize("gene", children, |key| key.starts_with("gene_"))
+            });
+            gff.entries.push(link(gene_entry, Some(&gene.id), None));
+            for feature in gene.features {
+                gff.entries.push(link(feature, None, Some(&gene.id)));
+            }
+            for transcript in gene.transcripts {
+                let transcript_entry = transcript
+                    .transcript
+                    .expect("Every transcript has an entry by now");
+                gff.entries
+                    .push(link(transcript_entry, Some(&transcript.id), Some(&gene.id)));
+                for feature in transcript.features {
+                    gff.entries.push(link(feature, None, Some(&transcript.id)));
+                }
+            }
+        }
+        gff.entries.extend(unlinked);
+        gff
+    }
+
+    /// Writes the entries in the GTF format.
+    ///
+    /// The `gene_id` of each entry is the `ID` of its topmost ancestor, and its `transcript_id` is the `ID`
+    /// of the ancestor just below that. An entry with several parents, such as an exon shared between
+    /// transcripts, is written once for each distinct pair of them. Other reserved attributes are not
+    /// written, while any other attributes are written as `key "value";`.
+    /// Entries sharing an `ID` are written together, where the first of them appears
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the entries do not form a valid [`FeatureGraph`],
+    /// if an attribute value contains a character that cannot be written in GTF, or if writing fails
+    #[tracing::instrument(skip_all)]
+    pub fn write_gtf(&self, dst: &mut impl Write) -> Result<(), GffError> {
+        let graph = FeatureGraph::new(self)?;
+        for feature in graph.features() {
+            let mut ids: Vec<(&str, Option<&str>)> = Vec::new();
+            for lineage in lineages(&graph, feature) {
+                let gene_id = lineage[lineage.len() - 1].id().unwrap_or_default();
+                let transcript_id = lineage
+                    .len()
+                    .checked_sub(2)
+                    .map(|i| lineage[i])
+                    .and_then(Feature::id);
+                if !ids.contains(&(gene_id, transcript_id)) {
+                    ids.push((gene_id, transcript_id));
+                }
+            }
+            for &(gene_id, transcript_id) in &ids {
+                for entry in feature.entries() {
+                    write_entry(dst, entry, gene_id, transcript_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every chain of parents from `feature` up to a root, starting with `feature` itself
+fn lineages<'g, 'a>(
+    graph: &'g FeatureGraph<'a>,
+    feature: &'g Feature<'a>,
+) -> Vec<Vec<&'g Feature<'a>>> {
+    let mut lineages = graph
+        .parents(feature)
+        .flat_map(|parent| lineages(graph, parent))
+        .map(|mut lineage| {
+            lineage.insert(0, feature);
+            lineage
+        })
+        .collect::<Vec<_>>();
+    if lineages.is_empty() {
+        lineages.push(vec![feature]);
+    }
+    lineages
+}
+
+/// GTF has no escapes, so a value can't hold the quote around it or the `;` ending it
+fn check_gtf_value(value: &str) -> Result<&str, GffError> {
+    if value.contains(|c: char| c == '"' || c == ';' || c.is_control()) {
+        return Err(GffError::InvalidGtfValue {
+            value: value.into(),
+        });
+    }
+    Ok(value)
+}
+
+fn write_entry(
+    dst: &mut impl Write,
+    entry: &Entry,
+    gene_id: &str,
+    transcript_id: Option<&str>,
+) -> Result<(), GffError> {
+    // Every value is checked before writing, so an error never leaves half a line behind
+    let gene_id = check_gtf_value(gene_id)?;
+    let transcript_id = transcript_id.map(check_gtf_value).transpose()?;
+    let others = entry
+        .attrs
+        .other
+        .iter()
+        .flatten()
+        .filter(|(key, _)| !matches!(&**key, "gene_id" | "transcript_id"))
+        .map(|(key, vals)| {
+            let vals = vals.iter().map(|val| &**val).collect::<Vec<_>>().join(",");
+            check_gtf_value(&vals)?;
+            Ok((key, vals))
+        })
+        .collect::<Result<Vec<_>, GffError>>()?;
+    write!(
+        dst,
+        "{}\t{}\t{}\t{}\t{}\t",
+        &*entry.seq_id, &*entry.source, &*entry.feature_type, entry.range.start, entry.range.end
+    )?;
+    match entry.score {
+        Some(score) => write!(dst, "{score}\t")?,
+        None => write!(dst, ".\t")?,
+    }
+    match entry.strand {
+        Some(ref strand) => write!(dst, "{strand}\t")?,
+        None => write!(dst, ".\t")?,
+    }
+    match entry.phase {
+        Some(phase) => write!(dst, "{phase}\t")?,
+        None => write!(dst, ".\t")?,
+    }
+    write!(dst, "gene_id \"{gene_id}\";")?;
+    if let Some(transcript_id) = transcript_id {
+        write!(dst, " transcript_id \"{transcript_id}\";")?;
+    }
+    for (key, vals) in others {
+        write!(dst, " {key} \"{vals}\";")?;
+    }
+    writeln!(dst)?;
+    Ok(())
+}
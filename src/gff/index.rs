@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use super::{Entry, Strand, GFF};
+use crate::genomics::interval::OneBased;
+
+/// An entry's interval, with `end` exclusive, and the greatest `end` of its subtree
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    start: usize,
+    end: usize,
+    max_end: usize,
+    entry: usize,
+}
+
+/// The entries on one sequence, sorted by start and laid out as an implicit binary search tree
+/// in which the node at index `i` on level `k` has children `i ± 2^(k-1)`
+#[derive(Debug, Clone, Default)]
+struct Contig {
+    nodes: Vec<Node>,
+    /// Indices of `nodes`, sorted by end
+    by_end: Vec<usize>,
+    root_level: usize,
+}
+
+impl Contig {
+    fn new(mut nodes: Vec<Node>) -> Self {
+        nodes.sort_unstable_by_key(|node| (node.start, node.end));
+        let mut by_end = (0..nodes.len()).collect::<Vec<_>>();
+        by_end.sort_by_key(|&i| nodes[i].end);
+        let root_level = Self::augment(&mut nodes);
+        Self {
+            nodes,
+            by_end,
+            root_level,
+        }
+    }
+
+    /// Fills in `max_end` bottom up, returning the level of the root
+    fn augment(nodes: &mut [Node]) -> usize {
+        let n = nodes.len();
+        if n == 0 {
+            return 0;
+        }
+        // Leaves are the even indices. `last` tracks the greatest end of the rightmost subtree on each level,
+        // which stands in for right children that are past the end of the array
+        let mut last_i = 0;
+        let mut last = 0;
+        for i in (0..n).step_by(2) {
+            nodes[i].max_end = nodes[i].end;
+            last_i = i;
+            last = nodes[i].end;
+        }
+        let mut level = 1;
+        while 1 << level <= n {
+            let x = 1 << (level - 1);
+            for i in ((x << 1) - 1..n).step_by(x << 2) {
+                let left = nodes[i - x].max_end;
+                let right = if i + x < n {
+                    nodes[i + x].max_end
+                } else {
+                    last
+                };
+                nodes[i].max_end = nodes[i].end.max(left).max(right);
+            }
+            last_i = if last_i >> level & 1 == 1 {
+                last_i - x
+            } else {
+                last_i + x
+            };
+            if last_i < n {
+                last = last.max(nodes[last_i].max_end);
+            }
+            level += 1;
+        }
+        level - 1
+    }
+
+    /// The positions of the nodes overlapping `start..end`, in order of start
+    fn overlapping(&self, start: usize, end: usize) -> Vec<usize> {
+        let n = self.nodes.len();
+        let mut found = Vec::new();
+        if n == 0 {
+            return found;
+        }
+        // Each frame is a node, its level, and whether its left subtree has been searched yet
+        let mut stack = vec![((1 << self.root_level) - 1, self.root_level, false)];
+        while let Some((x, level, left_done)) = stack.pop() {
+            if level <= 3 {
+                // Small subtrees are faster to scan in order
+                let first = x >> level << level;
+                let last = (first + (1 << (level + 1)) - 1).min(n);
+                for i in first..last {
+                    if self.nodes[i].start >= end {
+                        break;
+                    }
+                    if start < self.nodes[i].end {
+                        found.push(i);
+                    }
+                }
+            } else if !left_done {
+                stack.push((x, level, true));
+                let left = x - (1 << (level - 1));
+                // A left child past the end of the array may still have children within it
+                if left >= n || self.nodes[left].max_end > start {
+                    stack.push((left, level - 1, false));
+                }
+            } else if x < n && self.nodes[x].start < end {
+                if start < self.nodes[x].end {
+                    found.push(x);
+                }
+                stack.push((x + (1 << (level - 1)), level - 1, false));
+            }
+        }
+        found
+    }
+}
+
+/// An index of the entries of a [`GFF`] by position, for finding the features at or near a locus.
+///
+/// Positions are counted from 1, like the start and end columns of an [`Entry`]. Queries take
+/// `O(log n)` time plus the number of entries returned, and only look at entries on the same sequence.
+///
+/// To index only some features, such as the genes on one strand, use [`IntervalIndex::filtered`]:
+/// ```
+/// # use transcriptase::gff::{index::IntervalIndex, Strand, GFF};
+/// # let gff = GFF::default();
+/// let genes = IntervalIndex::filtered(&gff, |entry| {
+///     &*entry.feature_type == "gene" && entry.strand == Some(Strand::Positive)
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct IntervalIndex<'a> {
+    entries: &'a [Entry],
+    contigs: HashMap<&'a str, Contig>,
+}
+
+impl<'a> IntervalIndex<'a> {
+    /// Indexes every entry of `gff`
+    #[must_use]
+    pub fn new(gff: &'a GFF) -> Self {
+        Self::filtered(gff, |_| true)
+    }
+
+    /// Indexes the entries of `gff` for which `predicate` returns `true`
+    #[tracing::instrument(skip_all)]
+    pub fn filtered(gff: &'a GFF, predicate: impl Fn(&Entry) -> bool) -> Self {
+        let mut nodes: HashMap<&str, Vec<Node>> = HashMap::new();
+        for (i, entry) in gff.entries.iter().enumerate() {
+            if predicate(entry) {
+                nodes.entry(&entry.seq_id).or_default().push(Node {
+                    start: entry.range.start,
+                    end: entry.range.end + 1,
+                    max_end: 0,
+                    entry: i,
+                });
+            }
+        }
+        Self {
+            entries: &gff.entries,
+            contigs: nodes
+                .into_iter()
+                .map(|(seq_id, nodes)| (seq_id, Contig::new(nodes)))
+                .collect(),
+        }
+    }
+
+    /// The number of entries in the index
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.contigs.values().map(|contig| contig.nodes.len()).sum()
+    }
+
+    /// Returns `true` if no entries are indexed
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The entries on `seq_id` that overlap `range` by at least one base, in order of start.
+    ///
+    /// The range can also be given as an inclusive `start..=end`
+    pub fn overlapping(
+        &self,
+        seq_id: &str,
+        range: impl Into<OneBased>,
+    ) -> impl Iterator<Item = &'a Entry> + '_ {
+        let range = range.into();
+        let (start, end) = (range.start, range.end + 1);
+        self.contigs
+            .get(seq_id)
+            .into_iter()
+            .flat_map(move |contig| {
+                let found = contig.overlapping(start, end);
+                found
+                    .into_iter()
+                    .map(move |i| &self.entries[contig.nodes[i].entry])
+            })
+    }
+
+    /// The entries on `seq_id` that contain `position`, in order of start
+    pub fn containing(
+        &self,
+        seq_id: &str,
+        position: usize,
+    ) -> impl Iterator<Item = &'a Entry> + '_ {
+        self.overlapping(seq_id, position..=position)
+    }
+
+    /// The entry ending closest before `position`, or the first if several end at the same place
+    #[must_use]
+    pub fn preceding(&self, seq_id: &str, position: usize) -> Option<&'a Entry> {
+        let contig = self.contigs.get(seq_id)?;
+        let before = contig
+            .by_end
+            .partition_point(|&i| contig.nodes[i].end <= position);
+        let last_end = contig.nodes[*contig.by_end.get(before.checked_sub(1)?)?].end;
+        let first = contig
+            .by_end
+            .partition_point(|&i| contig.nodes[i].end < last_end);
+        Some(&self.entries[contig.nodes[contig.by_end[first]].entry])
+    }
+
+    /// The entry starting closest after `position`, or the first if several start at the same place
+    #[must_use]
+    pub fn following(&self, seq_id: &str, position: usize) -> Option<&'a Entry> {
+        let contig = self.contigs.get(seq_id)?;
+        let after = contig.nodes.partition_point(|node| node.start <= position);
+        Some(&self.entries[contig.nodes.get(after)?.entry])
+    }
+
+    /// The nearest entry upstream of `position` that does not contain it, reading along `strand`,
+    /// which is before it unless `strand` is [`Strand::Negative`]
+    #[must_use]
+    pub fn upstream(&self, seq_id: &str, position: usize, strand: &Strand) -> Option<&'a Entry> {
+        match strand {
+            Strand::Negative => self.following(seq_id, position),
+            _ => self.preceding(seq_id, position),
+        }
+    }
+
+    /// The nearest entry downstream of `position` that does not contain it, reading along `strand`,
+    /// which is after it unless `strand` is [`Strand::Negative`]
+    #[must_use]
+    pub fn downstream(&self, seq_id: &str, position: usize, strand: &Strand) -> Option<&'a Entry> {
+        match strand {
+            Strand::Negative => self.preceding(seq_id, position),
+            _ => self.following(seq_id, position),
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
-    ops::Range,
+    fmt,
+    str::FromStr,
 };
 
-use super::{GffError, UnescapedString};
-use crate::gff::parsers::ParseError;
+use super::{is_column_char, is_seq_id_char, Escaped, GffError, UnescapedString};
+use crate::genomics::interval::OneBased;
+use crate::gff::parsers::{range_bound, start_bound, ParseError};
 use nom::{
     branch::alt,
     bytes::complete::is_a,
@@ -15,19 +17,152 @@ use nom::{
 };
 use nom_supreme::{final_parser::final_parser, tag::complete::tag, ParserExt};
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Metadata {
     pub version: Option<(u8, u8)>,
-    pub sequence_regions: Option<HashMap<UnescapedString, Range<u64>>>,
-    pub feature_ontology_uri: Option<UnescapedString>,
-    pub attribute_ontology_uri: Option<UnescapedString>,
-    pub source_ontology_uri: Option<UnescapedString>,
-    pub species_uri: Option<UnescapedString>,
-    pub genome_build: Option<(UnescapedString, UnescapedString)>,
+    pub sequence_regions: Option<HashMap<UnescapedString, OneBased>>,
+    pub feature_ontology_uri: Option<Uri>,
+    pub attribute_ontology_uri: Option<Uri>,
+    pub source_ontology_uri: Option<Uri>,
+    pub species_uri: Option<Uri>,
+    pub genome_build: Option<GenomeBuild>,
     pub other_meta: Option<HashMap<UnescapedString, UnescapedString>>,
+    /// Any other `##` directives, without their leading `#`s, in the order they were read
+    pub other_directives: Option<Vec<Box<str>>>,
+}
+
+/// The assembly the sequences come from, as given by `##genome-build`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenomeBuild {
+    /// The organisation that made the assembly, such as `NCBI`
+    pub source: UnescapedString,
+    /// The name of the assembly, such as `GRCh38.p14`
+    pub name: UnescapedString,
+}
+
+/// A URI, kept as it was written.
+///
+/// Only the characters allowed by RFC 3986 are accepted, and the URI must start with a scheme,
+/// but its parts are not otherwise checked
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Uri(Box<str>);
+
+impl Uri {
+    /// Checks that `src` is a URI
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `src` does not start with a scheme,
+    /// or contains a character that cannot appear in a URI or a malformed percent-escape
+    pub fn new(src: &str) -> Result<Self, GffError> {
+        let (scheme, _) = src.split_once(':').ok_or(GffError::InvalidUri)?;
+        let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+        if !valid_scheme {
+            return Err(GffError::InvalidUri);
+        }
+        let bytes = src.as_bytes();
+        for (i, &b) in bytes.iter().enumerate() {
+            let valid = if b == b'%' {
+                bytes
+                    .get(i + 1..i + 3)
+                    .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            } else {
+                b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=".contains(&b)
+            };
+            if !valid {
+                return Err(GffError::InvalidUri);
+            }
+        }
+        Ok(Self(src.into()))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The scheme, such as `https`
+    #[must_use]
+    pub fn scheme(&self) -> &str {
+        self.0.split_once(':').map_or(&self.0, |(scheme, _)| scheme)
+    }
+
+    /// Everything after the scheme, up to the fragment
+    fn hierarchy(&self) -> &str {
+        let rest = &self.0[self.scheme().len() + 1..];
+        rest.split_once('#').map_or(rest, |(rest, _)| rest)
+    }
+
+    /// The host and port or other authority following `//`, if there is one
+    #[must_use]
+    pub fn authority(&self) -> Option<&str> {
+        let rest = self.hierarchy().strip_prefix("//")?;
+        Some(rest.find(['/', '?']).map_or(rest, |end| &rest[..end]))
+    }
+
+    /// The path, which may be empty
+    #[must_use]
+    pub fn path(&self) -> &str {
+        let rest = self.hierarchy();
+        let rest = match self.authority() {
+            Some(authority) => &rest[authority.len() + 2..],
+            None => rest,
+        };
+        rest.split_once('?').map_or(rest, |(path, _)| path)
+    }
+
+    /// The query, without its leading `?`
+    #[must_use]
+    pub fn query(&self) -> Option<&str> {
+        self.hierarchy().split_once('?').map(|(_, query)| query)
+    }
+
+    /// The fragment, without its leading `#`
+    #[must_use]
+    pub fn fragment(&self) -> Option<&str> {
+        self.0.split_once('#').map(|(_, fragment)| fragment)
+    }
+
+    /// The value of the first `key=value` pair named `key` in the query, still percent-escaped
+    #[must_use]
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query()?
+            .split(['&', ';'])
+            .filter_map(|pair| pair.split_once('='))
+            .find(|&(other, _)| other == key)
+            .map(|(_, val)| val)
+    }
+}
+
+impl FromStr for Uri {
+    type Err = GffError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 impl Metadata {
+    /// The NCBI Taxonomy ID of the species, read from the `id` of a `##species` URI
+    /// such as `https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?id=9606`
+    #[must_use]
+    pub fn taxon_id(&self) -> Option<u32> {
+        let uri = self.species_uri.as_ref()?;
+        uri.authority()?
+            .ends_with("ncbi.nlm.nih.gov")
+            .then(|| uri.query_param("id")?.parse().ok())
+            .flatten()
+    }
+
     #[tracing::instrument(skip(self))]
     pub(crate) fn parse_line(&mut self, domain: bool, src: &str) -> Result<(), GffError> {
         if domain {
@@ -39,11 +174,21 @@ impl Metadata {
 
     #[tracing::instrument(skip(self))]
     pub(crate) fn parse_metadata(&mut self, line: &str) -> Result<(), GffError> {
-        let (kind, rem) = line.split_once(' ').ok_or(GffError::MalformedLine)?;
+        let (kind, rem) = line.split_once(' ').unwrap_or((line, ""));
+        // Every known directive needs a value, but others may be bare, like `##processed`
+        let value = || {
+            let value = rem.trim_end();
+            if value.is_empty() {
+                return Err(GffError::MissingDirectiveValue {
+                    directive: kind.into(),
+                });
+            }
+            Ok(value)
+        };
         match kind {
-            "gff-version" => self.version = Metadata::parse_version(rem)?,
+            "gff-version" => self.version = Metadata::parse_version(value()?)?,
             "sequence-region" => {
-                let (seq_id, range) = Metadata::parse_sequence_region(rem)?;
+                let (seq_id, range) = Metadata::parse_sequence_region(value()?)?;
                 if let Some(ref mut map) = self.sequence_regions {
                     match map.entry(UnescapedString::new(seq_id)?) {
                         Entry::Occupied(_) => {
@@ -56,19 +201,23 @@ impl Metadata {
                         Some(HashMap::from([(UnescapedString::new(seq_id)?, range)]));
                 }
             }
-            //TODO: Add proper uri parsing to ontology & species metaattributes
-            "feature-ontology" => self.feature_ontology_uri = Some(UnescapedString::new(rem)?),
-            "attribute-ontology" => self.attribute_ontology_uri = Some(UnescapedString::new(rem)?),
-            "source-ontology" => self.source_ontology_uri = Some(UnescapedString::new(rem)?),
-            "species" => self.species_uri = Some(UnescapedString::new(rem)?),
+            "feature-ontology" => self.feature_ontology_uri = Some(Uri::new(value()?)?),
+            "attribute-ontology" => self.attribute_ontology_uri = Some(Uri::new(value()?)?),
+            "source-ontology" => self.source_ontology_uri = Some(Uri::new(value()?)?),
+            "species" => self.species_uri = Some(Uri::new(value()?)?),
             "genome-build" => {
-                let genome_build = rem
+                let (source, name) = value()?
                     .split_once(' ')
-                    .ok_or(GffError::InvalidGenomeBuild)
-                    .and_then(|(l, r)| Ok((UnescapedString::new(l)?, UnescapedString::new(r)?)))?;
-                self.genome_build = Some(genome_build);
+                    .ok_or(GffError::InvalidGenomeBuild)?;
+                self.genome_build = Some(GenomeBuild {
+                    source: UnescapedString::new(source)?,
+                    name: UnescapedString::new(name)?,
+                });
             }
-            _ => return Err(GffError::InvalidAttribute),
+            _ => self
+                .other_directives
+                .get_or_insert_with(Vec::new)
+                .push(line.into()),
         };
         Ok(())
     }
@@ -108,20 +257,91 @@ impl Metadata {
         )))(ver)
     }
 
-    fn parse_sequence_region(seq_region: &str) -> Result<(&str, Range<u64>), ParseError> {
+    fn parse_sequence_region(seq_region: &str) -> Result<(&str, OneBased), ParseError> {
         const VALID: &str =
             "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.:^*$@!+_?-|%>";
         final_parser::<_, _, VerboseError<&str>, ParseError>(map(
             separated_pair(
                 is_a(VALID).verify(|&id: &&str| !id.starts_with('>')),
                 char(' '),
-                separated_pair(
-                    nom::character::complete::u64,
-                    char(' '),
-                    nom::character::complete::u64,
-                ),
+                separated_pair(start_bound, char(' '), range_bound),
             ),
-            |(seq_id, (start, end))| (seq_id, start..end),
+            |(seq_id, (start, end))| (seq_id, OneBased::new(start, end)),
         ))(seq_region)
     }
 }
+
+/// Writes the metadata as `##` directives, starting with `##gff-version`.
+///
+/// Sequence regions and `#!` attributes are written sorted, since their original order is not kept
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// Directive values are split from their names at the first space
+        fn is_word_char(c: char) -> bool {
+            is_column_char(c) && c != ' '
+        }
+
+        match self.version {
+            Some((minor, revision)) => writeln!(f, "##gff-version 3.{minor}.{revision}")?,
+            None => writeln!(f, "##gff-version 3")?,
+        }
+        if let Some(ref regions) = self.sequence_regions {
+            let mut regions = regions
+                .iter()
+                .map(|(seq_id, range)| (&**seq_id, range))
+                .collect::<Vec<_>>();
+            regions.sort_unstable_by_key(|&(seq_id, _)| seq_id);
+            for (seq_id, range) in regions {
+                writeln!(
+                    f,
+                    "##sequence-region {} {} {}",
+                    Escaped(seq_id, is_seq_id_char),
+                    range.start,
+                    range.end
+                )?;
+            }
+        }
+        let uris = [
+            ("feature-ontology", &self.feature_ontology_uri),
+            ("attribute-ontology", &self.attribute_ontology_uri),
+            ("source-ontology", &self.source_ontology_uri),
+            ("species", &self.species_uri),
+        ];
+        for (directive, uri) in uris {
+            if let Some(uri) = uri {
+                writeln!(f, "##{directive} {uri}")?;
+            }
+        }
+        if let Some(GenomeBuild {
+            ref source,
+            ref name,
+        }) = self.genome_build
+        {
+            writeln!(
+                f,
+                "##genome-build {} {}",
+                Escaped(source, is_word_char),
+                Escaped(name, is_column_char)
+            )?;
+        }
+        for directive in self.other_directives.iter().flatten() {
+            writeln!(f, "##{directive}")?;
+        }
+        if let Some(ref others) = self.other_meta {
+            let mut others = others
+                .iter()
+                .map(|(key, val)| (&**key, val))
+                .collect::<Vec<_>>();
+            others.sort_unstable_by_key(|&(key, _)| key);
+            for (key, val) in others {
+                writeln!(
+                    f,
+                    "#!{} {}",
+                    Escaped(key, is_word_char),
+                    Escaped(val, is_column_char)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
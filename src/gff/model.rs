@@ -0,0 +1,290 @@
+use super::{
+    graph::{Feature, FeatureGraph},
+    ontology::Term,
+    Entry, GffError, Strand, GFF,
+};
+use crate::genomics::interval::OneBased;
+
+/// A gene and its transcripts, as built by [`GFF::genes`]
+#[derive(Debug, Clone)]
+pub struct Gene<'a> {
+    pub id: Option<&'a str>,
+    /// The first line of the gene, if it spans several
+    pub entry: &'a Entry,
+    /// The positions covered by every line of the gene
+    pub range: OneBased,
+    pub transcripts: Vec<Transcript<'a>>,
+}
+
+/// A transcript and its parts, each ordered in the direction of transcription,
+/// which is from the highest position down on the [`Strand::Negative`] strand
+#[derive(Debug, Clone)]
+pub struct Transcript<'a> {
+    pub id: Option<&'a str>,
+    /// The first line of the transcript, if it spans several
+    pub entry: &'a Entry,
+    /// The positions covered by every line of the transcript
+    pub range: OneBased,
+    /// The exons, or the CDS segments if the transcript has no exons
+    pub exons: Vec<Exon<'a>>,
+    pub cds: Vec<Cds<'a>>,
+    five_prime_utrs: Vec<&'a Entry>,
+    three_prime_utrs: Vec<&'a Entry>,
+}
+
+/// An exon of a [`Transcript`]
+#[derive(Debug, Clone, Copy)]
+pub struct Exon<'a> {
+    pub entry: &'a Entry,
+}
+
+/// A coding segment of a [`Transcript`]
+#[derive(Debug, Clone, Copy)]
+pub struct Cds<'a> {
+    pub entry: &'a Entry,
+}
+
+impl Exon<'_> {
+    /// The positions of this exon
+    #[must_use]
+    pub fn range(&self) -> OneBased {
+        self.entry.range
+    }
+
+    /// The number of bases in this exon
+    #[must_use]
+    pub fn length(&self) -> usize {
+        self.entry.range.len()
+    }
+}
+
+impl Cds<'_> {
+    /// The positions of this segment
+    #[must_use]
+    pub fn range(&self) -> OneBased {
+        self.entry.range
+    }
+
+    /// The number of bases in this segment
+    #[must_use]
+    pub fn length(&self) -> usize {
+        self.entry.range.len()
+    }
+
+    /// The number of bases before the first whole codon of this segment
+    #[must_use]
+    pub fn phase(&self) -> u8 {
+        self.entry.phase.unwrap_or(0)
+    }
+}
+
+impl<'a> Transcript<'a> {
+    fn is_negative(&self) -> bool {
+        self.entry.strand == Some(Strand::Negative)
+    }
+
+    /// The number of bases in the spliced transcript
+    #[must_use]
+    pub fn length(&self) -> usize {
+        self.exons.iter().map(Exon::length).sum()
+    }
+
+    /// The number of bases in the spliced coding sequence, the sum of the lengths of its segments
+    #[must_use]
+    pub fn cds_length(&self) -> usize {
+        self.cds.iter().map(Cds::length).sum()
+    }
+
+    /// Returns `true` if the transcript has a coding sequence
+    #[must_use]
+    pub fn is_coding(&self) -> bool {
+        !self.cds.is_empty()
+    }
+
+    /// The positions between each pair of consecutive exons, in the direction of transcription
+    #[must_use]
+    pub fn introns(&self) -> Vec<OneBased> {
+        let mut exons = self.exons.iter().map(Exon::range).collect::<Vec<_>>();
+        exons.sort_unstable();
+        let mut introns = exons
+            .windows(2)
+            .map(|pair| {
+                OneBased::new(
+                    pair[0].end.saturating_add(1),
+                    pair[1].start.saturating_sub(1),
+                )
+            })
+            .filter(|intron| !intron.is_empty())
+            .collect::<Vec<_>>();
+        if self.is_negative() {
+            introns.reverse();
+        }
+        introns
+    }
+
+    /// Every coding position, in the direction of transcription
+    fn coding_positions(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        let is_negative = self.is_negative();
+        self.cds.iter().flat_map(move |cds| {
+            let range = cds.range();
+            (0..range.len()).map(move |i| {
+                if is_negative {
+                    range.end - i
+                } else {
+                    range.start + i
+                }
+            })
+        })
+    }
+
+    /// The positions of the bases of the first whole codon, in the direction of transcription,
+    /// which may be split across segments
+    #[must_use]
+    pub fn start_codon(&self) -> Option<[usize; 3]> {
+        let phase = self.cds.first()?.phase().into();
+        let mut codon = self.coding_positions().skip(phase);
+        Some([codon.next()?, codon.next()?, codon.next()?])
+    }
+
+    /// The positions of the bases of the last codon, in the direction of transcription,
+    /// assuming as in GFF3 that the coding sequence includes the stop codon
+    #[must_use]
+    pub fn stop_codon(&self) -> Option<[usize; 3]> {
+        let mut codon = self.coding_positions().rev();
+        let [third, second, first] = [codon.next()?, codon.next()?, codon.next()?];
+        Some([first, second, third])
+    }
+
+    /// The parts of exons before the coding sequence, in the direction of transcription.
+    ///
+    /// These are the `five_prime_UTR` features of the transcript if it has any,
+    /// or are otherwise inferred from its exons and CDS
+    #[must_use]
+    pub fn five_prime_utrs(&self) -> Vec<OneBased> {
+        self.utrs(&self.five_prime_utrs, true)
+    }
+
+    /// The parts of exons after the coding sequence, in the direction of transcription.
+    ///
+    /// These are the `three_prime_UTR` features of the transcript if it has any,
+    /// or are otherwise inferred from its exons and CDS
+    #[must_use]
+    pub fn three_prime_utrs(&self) -> Vec<OneBased> {
+        self.utrs(&self.three_prime_utrs, false)
+    }
+
+    fn utrs(&self, explicit: &[&Entry], five_prime: bool) -> Vec<OneBased> {
+        if !explicit.is_empty() {
+            return explicit.iter().map(|entry| entry.range).collect();
+        }
+        let (Some(first), Some(last)) = (
+            self.cds.iter().map(|cds| cds.entry.range.start).min(),
+            self.cds.iter().map(|cds| cds.entry.range.end).max(),
+        ) else {
+            return Vec::new();
+        };
+        // Upstream of the CDS lies before it in the sequence unless transcribed from the negative strand
+        let before = five_prime != self.is_negative();
+        self.exons
+            .iter()
+            .filter_map(|exon| {
+                let OneBased { start, end } = exon.range();
+                if before {
+                    (start < first).then(|| OneBased::new(start, end.min(first - 1)))
+                } else {
+                    (end > last).then(|| OneBased::new(start.max(last + 1), end))
+                }
+            })
+            .collect()
+    }
+}
+
+/// The entries of `features`, ordered by start, or by end from the highest if `is_negative`
+fn segments<'a>(features: &[&Feature<'a>], is_negative: bool) -> Vec<&'a Entry> {
+    let mut segments = features
+        .iter()
+        .flat_map(|feature| feature.entries())
+        .copied()
+        .collect::<Vec<_>>();
+    segments.sort_unstable_by_key(|entry| entry.range.start);
+    if is_negative {
+        segments.reverse();
+    }
+    segments
+}
+
+/// The positions from the lowest start to the highest end of the lines of `feature`
+fn span(feature: &Feature) -> OneBased {
+    let entries = feature.entries();
+    OneBased::new(
+        entries
+            .iter()
+            .map(|entry| entry.range.start)
+            .min()
+            .unwrap_or(1),
+        entries
+            .iter()
+            .map(|entry| entry.range.end)
+            .max()
+            .unwrap_or(0),
+    )
+}
+
+fn is_a(feature: &Feature, name: &str) -> bool {
+    let term = Term::get(name).expect("Gene models only use embedded terms");
+    Term::get(feature.feature_type()).is_some_and(|feature_term| feature_term.is_a(term))
+}
+
+impl GFF {
+    /// Builds the gene models of the entries, from every feature whose type is a kind of `gene`
+    /// in the [Sequence Ontology](Term) and its children that are kinds of `transcript`.
+    ///
+    /// The `exon`, `CDS`, `five_prime_UTR` and `three_prime_UTR` children of each transcript,
+    /// including kinds of them, make up its parts
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the entries do not form a valid [`FeatureGraph`]
+    #[tracing::instrument(skip_all)]
+    pub fn genes(&self) -> Result<Vec<Gene<'_>>, GffError> {
+        let graph = FeatureGraph::new(self)?;
+        let genes = graph
+            .features()
+            .iter()
+            .filter(|feature| is_a(feature, "gene"))
+            .map(|gene| Gene {
+                id: gene.id(),
+                entry: gene.entries()[0],
+                range: span(gene),
+                transcripts: graph
+                    .children(gene)
+                    .filter(|feature| is_a(feature, "transcript"))
+                    .map(|transcript| {
+                        let entry = transcript.entries()[0];
+                        let is_negative = entry.strand == Some(Strand::Negative);
+                        let parts = |name| {
+                            let parts = graph
+                                .children(transcript)
+                                .filter(|child| is_a(child, name))
+                                .collect::<Vec<_>>();
+                            segments(&parts, is_negative)
+                        };
+                        let cds = parts("CDS");
+                        let exons = parts("exon");
+                        let exons = if exons.is_empty() { &cds } else { &exons };
+                        Transcript {
+                            id: transcript.id(),
+                            entry,
+                            range: span(transcript),
+                            exons: exons.iter().map(|&entry| Exon { entry }).collect(),
+                            cds: cds.iter().map(|&entry| Cds { entry }).collect(),
+                            five_prime_utrs: parts("five_prime_UTR"),
+                            three_prime_utrs: parts("three_prime_UTR"),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(genes)
+    }
+}
@@ -0,0 +1,133 @@
+use phf::{phf_map, Map};
+
+use super::Entry;
+
+/// A term of the Sequence Ontology, the vocabulary of GFF3 feature types.
+///
+/// Only the subset of terms commonly used in genome annotation is embedded, with its `is_a` and `part_of`
+/// relationships simplified to the ones that matter for annotation.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Term {
+    /// The accession, such as `SO:0000704`
+    pub accession: &'static str,
+    /// The name, such as `gene`
+    pub name: &'static str,
+    is_a: &'static [&'static str],
+    part_of: &'static [&'static str],
+}
+
+macro_rules! terms {
+    ($($name:literal => $accession:literal $(is_a [$($is_a:literal),*])? $(part_of [$($part_of:literal),*])?),* $(,)?) => {
+        static TERMS: Map<&'static str, Term> = phf_map! {
+            $($name => Term {
+                accession: $accession,
+                name: $name,
+                is_a: &[$($($is_a),*)?],
+                part_of: &[$($($part_of),*)?],
+            }),*
+        };
+
+        static ACCESSIONS: Map<&'static str, &'static str> = phf_map! {
+            $($accession => $name),*
+        };
+    };
+}
+
+terms! {
+    "region" => "SO:0000001",
+    "biological_region" => "SO:0001411" is_a ["region"],
+    "chromosome" => "SO:0000340" is_a ["region"],
+    "contig" => "SO:0000149" is_a ["region"],
+    "gene" => "SO:0000704" is_a ["biological_region"],
+    "protein_coding_gene" => "SO:0001217" is_a ["gene"],
+    "ncRNA_gene" => "SO:0001263" is_a ["gene"],
+    "pseudogene" => "SO:0000336" is_a ["biological_region"],
+    "operon" => "SO:0000178" is_a ["biological_region"],
+    "transcript" => "SO:0000673" is_a ["biological_region"] part_of ["gene", "operon"],
+    "primary_transcript" => "SO:0000185" is_a ["transcript"],
+    "mature_transcript" => "SO:0000233" is_a ["transcript"],
+    "mRNA" => "SO:0000234" is_a ["mature_transcript"],
+    "ncRNA" => "SO:0000655" is_a ["mature_transcript"],
+    "lnc_RNA" => "SO:0001877" is_a ["ncRNA"],
+    "tRNA" => "SO:0000253" is_a ["ncRNA"],
+    "rRNA" => "SO:0000252" is_a ["ncRNA"],
+    "snRNA" => "SO:0000274" is_a ["ncRNA"],
+    "snoRNA" => "SO:0000275" is_a ["ncRNA"],
+    "miRNA" => "SO:0000276" is_a ["ncRNA"],
+    "pseudogenic_transcript" => "SO:0000516" is_a ["biological_region"] part_of ["pseudogene"],
+    "exon" => "SO:0000147" is_a ["biological_region"] part_of ["transcript", "pseudogenic_transcript"],
+    "coding_exon" => "SO:0000195" is_a ["exon"],
+    "noncoding_exon" => "SO:0000198" is_a ["exon"],
+    "intron" => "SO:0000188" is_a ["biological_region"] part_of ["transcript"],
+    "CDS" => "SO:0000316" is_a ["biological_region"] part_of ["mRNA"],
+    "UTR" => "SO:0000203" is_a ["biological_region"] part_of ["mRNA"],
+    "five_prime_UTR" => "SO:0000204" is_a ["UTR"],
+    "three_prime_UTR" => "SO:0000205" is_a ["UTR"],
+    "start_codon" => "SO:0000318" is_a ["biological_region"] part_of ["CDS"],
+    "stop_codon" => "SO:0000319" is_a ["biological_region"] part_of ["CDS"],
+    "polypeptide" => "SO:0000104" is_a ["biological_region"],
+    "TSS" => "SO:0000315" is_a ["biological_region"] part_of ["transcript"],
+    "polyA_site" => "SO:0000553" is_a ["biological_region"] part_of ["mRNA"],
+    "promoter" => "SO:0000167" is_a ["biological_region"],
+    "enhancer" => "SO:0000165" is_a ["biological_region"],
+    "repeat_region" => "SO:0000657" is_a ["biological_region"],
+    "transposable_element" => "SO:0000101" is_a ["biological_region"],
+    "origin_of_replication" => "SO:0000296" is_a ["biological_region"],
+    "match" => "SO:0000343" is_a ["region"],
+    "nucleotide_match" => "SO:0000347" is_a ["match"],
+    "cDNA_match" => "SO:0000689" is_a ["nucleotide_match"],
+    "EST_match" => "SO:0000668" is_a ["nucleotide_match"],
+    "protein_match" => "SO:0000349" is_a ["match"],
+    "match_part" => "SO:0000039" is_a ["region"] part_of ["match"],
+}
+
+impl Term {
+    /// Looks up a term by its name or its `SO:` accession
+    #[must_use]
+    pub fn get(name_or_accession: &str) -> Option<&'static Self> {
+        let name = ACCESSIONS
+            .get(name_or_accession)
+            .copied()
+            .unwrap_or(name_or_accession);
+        TERMS.get(name)
+    }
+
+    fn all(names: &'static [&'static str]) -> impl Iterator<Item = &'static Self> {
+        names.iter().map(|name| {
+            TERMS
+                .get(name)
+                .expect("Relationships only name embedded terms")
+        })
+    }
+
+    /// The terms this term is directly a kind of
+    pub fn supertypes(&self) -> impl Iterator<Item = &'static Self> {
+        Self::all(self.is_a)
+    }
+
+    /// Returns `true` if this term is `other` or a kind of it, such as `lnc_RNA` for `transcript`
+    #[must_use]
+    pub fn is_a(&self, other: &Self) -> bool {
+        self == other || self.supertypes().any(|supertype| supertype.is_a(other))
+    }
+
+    /// Returns `true` if a feature of this term can be part of a feature of `other`,
+    /// directly or through intermediate features, such as `exon` for `mRNA` or `CDS` for `gene`.
+    ///
+    /// A kind of a term can be part of whatever the term can, and part of any kind of it.
+    #[must_use]
+    pub fn is_part_of(&self, other: &Self) -> bool {
+        Self::all(self.part_of).any(|whole| other.is_a(whole) || whole.is_part_of(other))
+            || self
+                .supertypes()
+                .any(|supertype| supertype.is_part_of(other))
+    }
+}
+
+impl Entry {
+    /// The Sequence Ontology term of this entry's type, if it is one of the embedded terms
+    #[must_use]
+    pub fn term(&self) -> Option<&'static Term> {
+        Term::get(&self.feature_type)
+    }
+}
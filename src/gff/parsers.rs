@@ -29,7 +29,7 @@ impl ExtractContext<&str, ParseError> for VerboseError<&str> {
         let (fail, kind) = &self
             .errors
             .get(1)
-            .unwrap_or_else(|| self.errors.get(0).expect("There is at least one error"));
+            .unwrap_or_else(|| self.errors.first().expect("There is at least one error"));
         let reason = match kind {
             VerboseErrorKind::Context(ctx) => ctx,
             VerboseErrorKind::Nom(e) => e.description(),
@@ -75,6 +75,14 @@ pub(crate) fn range_bound(src: &str) -> NomResult<'_, usize> {
         .parse(src)
 }
 
+/// The start of a range, which must be at least 1 since positions are counted from 1
+pub(crate) fn start_bound(src: &str) -> NomResult<'_, usize> {
+    range_bound
+        .verify(|&start| start >= 1)
+        .context("Positions are counted from 1, so a range cannot start at 0")
+        .parse(src)
+}
+
 pub(crate) fn score(src: &str) -> NomResult<'_, Option<f64>> {
     alt((value(None, char('.')), map(double, Some)))
         .context("Invalid score, expected one of '.' or a valid floating point number")
@@ -130,7 +138,7 @@ pub(crate) fn entry(src: &str) -> NomResult<'_, RawEntry<'_>> {
         terminated(seq_id, tag("\t")),
         terminated(source, tag("\t")),
         terminated(feature_type, tag("\t")),
-        terminated(range_bound, tag("\t")),
+        terminated(start_bound, tag("\t")),
         terminated(range_bound, tag("\t")),
         terminated(score, tag("\t")),
         terminated(strand, tag("\t")),
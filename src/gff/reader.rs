@@ -0,0 +1,255 @@
+use std::io::BufRead;
+
+use super::{borrowed::EntryRef, meta::Metadata, Entry, GffError};
+use crate::{fasta::Fasta, genomics::genome::DnaSeq};
+
+/// A single item of a GFF3 file, as read by a [`GffReader`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GffItem {
+    /// A `##` or `#!` directive without its leading `#`s, which has also been added to [`GffReader::metadata`]
+    Directive(Box<str>),
+    /// A `###` directive: every feature read so far is complete, and no later entry refers back to one
+    ResolutionBarrier,
+    /// A comment line without its leading `#`
+    Comment(Box<str>),
+    Entry(Box<Entry>),
+    /// A single FASTA formatted sequence from the section that makes up the rest of the file,
+    /// following a `##FASTA` directive or starting at the first line beginning with `>`
+    Sequence(Box<Fasta<DnaSeq>>),
+}
+
+/// What a single line of a GFF3 file holds.
+///
+/// Every way of reading a file tells its lines apart with [`Line::classify`], so they all agree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Line<'a> {
+    /// An empty or whitespace-only line
+    Blank,
+    /// A `###` directive
+    ResolutionBarrier,
+    /// The start of the FASTA section, which runs to the end of the file: either a `##FASTA` directive,
+    /// or the header of the first sequence if `header` is set
+    Fasta {
+        header: bool,
+    },
+    /// A `##` directive without its leading `#`s
+    Directive(&'a str),
+    /// A `#!` directive without its leading `#!`
+    DomainDirective(&'a str),
+    /// A comment without its leading `#`
+    Comment(&'a str),
+    Entry(&'a str),
+}
+
+impl<'a> Line<'a> {
+    /// Classifies a line, which may still end with its line ending
+    pub(crate) fn classify(line: &'a str) -> Self {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            Self::Blank
+        } else if line.trim_end() == "###" {
+            Self::ResolutionBarrier
+        } else if line.trim_end() == "##FASTA" {
+            Self::Fasta { header: false }
+        } else if line.starts_with('>') {
+            Self::Fasta { header: true }
+        } else if let Some(directive) = line.strip_prefix("##") {
+            Self::Directive(directive)
+        } else if let Some(directive) = line.strip_prefix("#!") {
+            Self::DomainDirective(directive)
+        } else if let Some(comment) = line.strip_prefix('#') {
+            Self::Comment(comment)
+        } else {
+            Self::Entry(line)
+        }
+    }
+}
+
+/// Reads a GFF3 file line by line, without holding its entries in memory
+#[derive(Debug)]
+pub struct GffReader<R> {
+    src: R,
+    buf: String,
+    line: usize,
+    metadata: Metadata,
+    /// Set once the FASTA section has been reached
+    in_fasta: bool,
+    /// The line number and text of a FASTA header that was read while looking for the end of a sequence
+    header: Option<(usize, String)>,
+    finished: bool,
+}
+
+impl<R: BufRead> GffReader<R> {
+    pub fn new(src: R) -> Self {
+        Self {
+            src,
+            buf: String::new(),
+            line: 0,
+            metadata: Metadata::default(),
+            in_fasta: false,
+            header: None,
+            finished: false,
+        }
+    }
+
+    /// The metadata from every directive read so far
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the metadata from every directive read so far
+    #[must_use]
+    pub fn into_metadata(self) -> Metadata {
+        self.metadata
+    }
+
+    /// Reads the next item along with its (1-based) line number, or returns `None` at the end of the input
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from the source fails or the next line is malformed,
+    /// as a [`GffError::AtLine`] holding the line number
+    #[tracing::instrument(skip(self))]
+    pub fn read_item(&mut self) -> Result<Option<(usize, GffItem)>, GffError> {
+        while !self.finished {
+            if self.in_fasta {
+                return self.read_sequence();
+            }
+            self.buf.clear();
+            if self.src.read_line(&mut self.buf)? == 0 {
+                self.finished = true;
+                break;
+            }
+            self.line += 1;
+            let line = self.line;
+            match self.parse_line() {
+                Ok(Some(item)) => return Ok(Some((line, item))),
+                Ok(None) => continue,
+                Err(source) => {
+                    return Err(GffError::AtLine {
+                        line,
+                        source: Box::new(source),
+                    })
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the next entry along with its (1-based) line number, borrowing it from the reader's buffer
+    /// instead of allocating, or returns `None` once there are no more entries.
+    ///
+    /// Directives are still added to [`GffReader::metadata`], while comments are skipped
+    /// and the `##FASTA` section is not read
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if reading from the source fails or a line is malformed,
+    /// as a [`GffError::AtLine`] holding the line number
+    pub fn read_entry_ref(&mut self) -> Result<Option<(usize, EntryRef<'_>)>, GffError> {
+        loop {
+            if self.finished {
+                return Ok(None);
+            }
+            self.buf.clear();
+            if self.src.read_line(&mut self.buf)? == 0 {
+                self.finished = true;
+                return Ok(None);
+            }
+            self.line += 1;
+            match Line::classify(&self.buf) {
+                Line::Fasta { .. } => self.finished = true,
+                Line::Directive(_) | Line::DomainDirective(_) => {
+                    self.parse_line().map_err(|source| GffError::AtLine {
+                        line: self.line,
+                        source: Box::new(source),
+                    })?;
+                }
+                Line::Entry(_) => break,
+                Line::Blank | Line::ResolutionBarrier | Line::Comment(_) => {}
+            }
+        }
+        let line = self.line;
+        EntryRef::parse(self.buf.trim_end_matches(['\r', '\n']))
+            .map(|entry| Some((line, entry)))
+            .map_err(|source| GffError::AtLine {
+                line,
+                source: Box::new(source),
+            })
+    }
+
+    /// Reads the next sequence of the FASTA section, up to the next header or the end of the input
+    fn read_sequence(&mut self) -> Result<Option<(usize, GffItem)>, GffError> {
+        loop {
+            let (line, mut record) = self
+                .header
+                .take()
+                .unwrap_or_else(|| (self.line + 1, String::new()));
+            loop {
+                self.buf.clear();
+                if self.src.read_line(&mut self.buf)? == 0 {
+                    self.finished = true;
+                    break;
+                }
+                self.line += 1;
+                if self.buf.starts_with('>') {
+                    self.header = Some((self.line, self.buf.clone()));
+                    break;
+                }
+                record.push_str(&self.buf);
+            }
+            if record.trim().is_empty() {
+                if self.finished {
+                    return Ok(None);
+                }
+                continue;
+            }
+            let mut records = Fasta::parse(&record).map_err(|e| GffError::AtLine {
+                line,
+                source: Box::new(e.into()),
+            })?;
+            // Only the first record can lack a header, and every other one ends at the next header
+            let record = records.remove(0);
+            return Ok(Some((line, GffItem::Sequence(Box::new(record)))));
+        }
+    }
+
+    fn parse_line(&mut self) -> Result<Option<GffItem>, GffError> {
+        let item = match Line::classify(&self.buf) {
+            Line::Blank => return Ok(None),
+            Line::ResolutionBarrier => GffItem::ResolutionBarrier,
+            Line::Fasta { header } => {
+                // The sequences run to the end of the file, and are read one at a time by `read_sequence`
+                self.in_fasta = true;
+                if header {
+                    self.header = Some((self.line, self.buf.clone()));
+                }
+                return Ok(None);
+            }
+            Line::Directive(directive) => {
+                self.metadata.parse_line(false, directive)?;
+                GffItem::Directive(directive.into())
+            }
+            Line::DomainDirective(directive) => {
+                self.metadata.parse_line(true, directive)?;
+                GffItem::Directive(directive.into())
+            }
+            Line::Comment(comment) => GffItem::Comment(comment.into()),
+            Line::Entry(line) => {
+                let mut entry = line.parse::<Entry>()?;
+                entry.line = Some(self.line);
+                GffItem::Entry(Box::new(entry))
+            }
+        };
+        Ok(Some(item))
+    }
+}
+
+impl<R: BufRead> Iterator for GffReader<R> {
+    type Item = Result<(usize, GffItem), GffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_item().transpose()
+    }
+}
@@ -1,7 +1,10 @@
 use proptest::prelude::*;
 
+use std::ops::RangeInclusive;
+
 use super::parsers::*;
 use super::AttributeSet;
+use crate::genomics::interval::OneBased;
 
 macro_rules! assume {
     ($e:expr) => {
@@ -37,7 +40,7 @@ strategy! {
     gen_score: String => s in any::<Option<f64>>().prop_map(|r| if let Some(r) = r { r.to_string() } else { ".".to_string() }),
     gen_strand: String => s in "[.+?-]",
     gen_phase: String => p in "[.012]",
-    gen_range: usize => r in any::<usize>().prop_filter("Ranges are 1 indexed and cannot be 0", |&r| (r != 0))
+    gen_range: usize => r in any::<usize>().prop_filter("Ranges are 1 indexed and cannot be 0", |&r| r != 0)
 }
 
 prop_compose! {
@@ -125,3 +128,933 @@ proptest! {
 fn no_uppercase_attr() {
     AttributeSet::parse("A=0").unwrap_err();
 }
+
+#[test]
+fn attribute_order_is_not_compared() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let a = AttributeSet::parse("ID=gene1;Name=EDEN;color=red").unwrap();
+    let b = AttributeSet::parse("color=red;Name=EDEN;ID=gene1").unwrap();
+    assert_eq!(a, b);
+    let hash = |attrs: &AttributeSet| {
+        let mut hasher = DefaultHasher::new();
+        attrs.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(a.to_string(), "ID=gene1;Name=EDEN;color=red");
+    assert_eq!(b.to_string(), "color=red;Name=EDEN;ID=gene1");
+}
+
+#[test]
+fn no_zero_start() {
+    use super::{Entry, GFF};
+
+    "ctg1\t.\tgene\t0\t10\t.\t+\t.\t."
+        .parse::<Entry>()
+        .unwrap_err();
+    "ctg1\t.\tmatch\t1\t10\t.\t+\t.\tTarget=est1 0 9"
+        .parse::<Entry>()
+        .unwrap_err();
+    "##gff-version 3\n##sequence-region ctg1 0 10\n"
+        .parse::<GFF>()
+        .unwrap_err();
+    let max = format!("ctg1\t.\tgene\t1\t{}\t.\t+\t.\t.", usize::MAX);
+    assert_eq!(max.parse::<Entry>().unwrap().range.len(), usize::MAX);
+}
+
+#[test]
+fn write_round_trip() {
+    const SRC: &str = "##gff-version 3.1.26
+##sequence-region ctg123 1 1497228
+##species https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?id=9606
+#!genome-build-accession NCBI_Assembly:GCF_000001405.39
+# A comment that is not kept
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tName=EDEN%3B1;ID=gene00001;note=a %26 b
+ctg123\t.\tmRNA\t1050\t9000\t0.5\t+\t.\tID=mRNA00001;Parent=gene00001;Gap=M8 D3 M6
+###
+ctg123\tEST\tmatch\t1300\t1500\t.\t-\t0\tID=match1;Target=EST23 1 21;Is_circular=false
+";
+    let gff = SRC.parse::<super::GFF>().unwrap();
+    assert_eq!(gff.entries.len(), 3);
+    assert_eq!(&*gff.entries[0].attrs.name.clone().unwrap(), "EDEN;1");
+
+    let written = gff.to_string();
+    assert!(written.starts_with("##gff-version 3.1.26\n"));
+    assert!(written.contains("\tName=EDEN%3B1;ID=gene00001;note=a %26 b\n"));
+    assert!(written.contains("\n###\nctg123\tEST"));
+    assert!(written.contains("Target=EST23 1 21;"));
+    assert_eq!(written.parse::<super::GFF>().unwrap(), gff);
+}
+
+#[test]
+fn streaming_reader() {
+    use super::{
+        reader::{GffItem, GffReader},
+        GffError,
+    };
+    use crate::fasta::Fasta;
+
+    const SRC: &str = "##gff-version 3
+# comment
+
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene00001
+###
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tid=bad;ID=gene00002;Alias
+##FASTA
+>ctg123
+ACGT
+";
+    let mut reader = GffReader::new(SRC.as_bytes());
+    assert_eq!(
+        reader.next().unwrap().unwrap(),
+        (1, GffItem::Directive("gff-version 3".into()))
+    );
+    assert_eq!(
+        reader.next().unwrap().unwrap(),
+        (2, GffItem::Comment(" comment".into()))
+    );
+    assert!(matches!(
+        reader.next().unwrap().unwrap(),
+        (4, GffItem::Entry(_))
+    ));
+    assert_eq!(
+        reader.next().unwrap().unwrap(),
+        (5, GffItem::ResolutionBarrier)
+    );
+    assert!(matches!(
+        reader.next().unwrap(),
+        Err(GffError::AtLine { line: 6, .. })
+    ));
+    assert_eq!(
+        reader.next().unwrap().unwrap(),
+        (
+            8,
+            GffItem::Sequence(Box::new(Fasta::parse(">ctg123\nACGT\n").unwrap().remove(0)))
+        )
+    );
+    assert!(reader.next().is_none());
+    assert_eq!(reader.metadata().version, None);
+}
+
+#[test]
+fn fasta_section() {
+    const SRC: &str = "##gff-version 3
+ctg123\t.\tgene\t1\t8\t.\t+\t.\tID=gene00001
+##FASTA
+>ctg123 test contig
+ACGTACGT
+ACGT
+>ctg124
+TTTT
+";
+    let gff = SRC.parse::<super::GFF>().unwrap();
+    assert_eq!(gff.sequences.len(), 2);
+    assert_eq!(
+        gff.sequences[0].description.as_deref(),
+        Some("ctg123 test contig")
+    );
+    assert_eq!(gff.sequences[0].sequence.to_string(), "ACGTACGTACGT\n");
+
+    // The reader hands back one sequence at a time, at the line of its header
+    let streamed = super::reader::GffReader::new(SRC.as_bytes())
+        .filter_map(|item| match item.unwrap() {
+            (line, super::reader::GffItem::Sequence(record)) => Some((line, *record)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        streamed,
+        [(4, gff.sequences[0].clone()), (7, gff.sequences[1].clone())]
+    );
+
+    let written = gff.to_string();
+    assert!(written.ends_with("##FASTA\n>ctg123 test contig\nACGTACGTACGT\n>ctg124\nTTTT\n"));
+    assert_eq!(written.parse::<super::GFF>().unwrap(), gff);
+
+    // A record without a description keeps a header, so it is not joined to the one before it
+    let mut gff = gff;
+    gff.sequences[1].description = None;
+    let written = gff.to_string();
+    assert!(written.ends_with("ACGTACGTACGT\n>\nTTTT\n"));
+    assert_eq!(written.parse::<super::GFF>().unwrap(), gff);
+}
+
+#[test]
+fn feature_graph() {
+    use super::{graph::FeatureGraph, GffError, GFF};
+
+    const SRC: &str = "##gff-version 3
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene1
+ctg123\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg123\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA2;Parent=gene1
+ctg123\t.\texon\t1050\t1500\t.\t+\t.\tParent=mRNA1,mRNA2
+ctg123\t.\tCDS\t1201\t1500\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg123\t.\tCDS\t3000\t3902\t.\t+\t0\tID=cds1;Parent=mRNA1
+";
+    let gff = SRC.parse::<GFF>().unwrap();
+    let graph = FeatureGraph::new(&gff).unwrap();
+    assert_eq!(graph.features().len(), 5);
+    assert_eq!(graph.roots().count(), 1);
+
+    let gene = graph.get("gene1").unwrap();
+    let ids = |features: Vec<_>| {
+        features
+            .into_iter()
+            .map(|f: &super::graph::Feature| f.id().unwrap_or(f.feature_type()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        ids(graph.descendants(gene)),
+        ["mRNA1", "exon", "cds1", "mRNA2"]
+    );
+    assert_eq!(graph.get("cds1").unwrap().entries().len(), 2);
+    let exon = graph.children(graph.get("mRNA2").unwrap()).next().unwrap();
+    assert_eq!(ids(graph.ancestors(exon)), ["mRNA1", "gene1", "mRNA2"]);
+
+    let dangling = format!("{SRC}ctg123\t.\texon\t1\t2\t.\t+\t.\tParent=mRNA3\n")
+        .parse::<GFF>()
+        .unwrap();
+    assert!(matches!(
+        FeatureGraph::new(&dangling),
+        Err(GffError::DanglingParent { parent, .. }) if &*parent == "mRNA3"
+    ));
+    let cycle = SRC
+        .replace("ID=gene1", "ID=gene1;Parent=cds1")
+        .parse::<GFF>()
+        .unwrap();
+    assert!(matches!(
+        FeatureGraph::new(&cycle),
+        Err(GffError::FeatureCycle { .. })
+    ));
+}
+
+#[test]
+fn extract_sequences() {
+    use crate::{
+        fasta::{Fasta, Sequence},
+        genomics::genome::DnaSeq,
+    };
+
+    const SRC: &str = "##gff-version 3
+ctg1\t.\tgene\t1\t20\t.\t+\t.\tID=gene1
+ctg1\t.\tmRNA\t1\t20\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg1\t.\texon\t1\t6\t.\t+\t.\tParent=mRNA1
+ctg1\t.\texon\t10\t20\t.\t+\t.\tParent=mRNA1
+ctg1\t.\tCDS\t4\t6\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tCDS\t10\t18\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tmRNA\t4\t9\t.\t-\t.\tID=mRNA2
+ctg1\t.\tCDS\t4\t9\t.\t-\t1\tParent=mRNA2
+";
+    let gff = SRC.parse::<super::GFF>().unwrap();
+    let genome = Fasta::<DnaSeq>::parse(">ctg1 test\nCCCATGCCCAAAGGGTAACCCCCCCCCCCC\n").unwrap();
+    let extracted = gff.extract_sequences(&genome).unwrap();
+    fn seqs<T: Sequence + std::fmt::Display>(records: &[Fasta<T>]) -> Vec<String> {
+        records
+            .iter()
+            .map(|record| record.sequence.to_string())
+            .collect()
+    }
+    assert_eq!(
+        seqs(&extracted.transcripts),
+        ["CCCATGAAAGGGTAACC\n", "GGGCAT\n"]
+    );
+    assert_eq!(seqs(&extracted.cds), ["ATGAAAGGGTAA\n", "GGCAT\n"]);
+    assert_eq!(seqs(&extracted.proteins), ["MKG*\n", "G\n"]);
+    assert_eq!(extracted.proteins[1].description.as_deref(), Some("mRNA2"));
+}
+
+#[test]
+fn gtf_conversion() {
+    use super::{graph::FeatureGraph, gtf::GtfReader, GffError, GFF};
+
+    const GTF: &str = "#!genome-build GRCh38
+1\thavana\tgene\t11869\t14409\t.\t+\t.\tgene_id \"ENSG1\"; gene_name \"DDX11L1\";
+1\thavana\ttranscript\t11869\t14409\t.\t+\t.\tgene_id \"ENSG1\"; transcript_id \"ENST1\"; tag \"basic\";
+1\thavana\texon\t11869\t12227\t.\t+\t.\tgene_id \"ENSG1\"; transcript_id \"ENST1\"; exon_number \"1\";
+1\thavana\texon\t12613\t12721\t.\t+\t.\tgene_id \"ENSG1\"; transcript_id \"ENST1\"; exon_number \"2\";
+";
+    let gff = GFF::read_gtf(&mut GTF.as_bytes()).unwrap();
+    assert_eq!(gff.entries.len(), 4);
+    let graph = FeatureGraph::new(&gff).unwrap();
+    assert_eq!(graph.descendants(graph.get("ENSG1").unwrap()).len(), 3);
+
+    let mut written = Vec::new();
+    gff.write_gtf(&mut written).unwrap();
+    assert_eq!(
+        String::from_utf8(written).unwrap(),
+        GTF.split_once('\n').unwrap().1
+    );
+
+    // Genes and transcripts are made up when only their features are given
+    let exons = GTF
+        .lines()
+        .filter(|line| line.contains("\texon\t"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let entries = GtfReader::new(exons.as_bytes())
+        .map(|entry| entry.map(|(_, entry)| entry))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let gff = GFF::from_gtf(entries);
+    let graph = FeatureGraph::new(&gff).unwrap();
+    let gene = graph.get("ENSG1").unwrap();
+    assert_eq!(gene.entries()[0].range, OneBased::new(11869, 12721));
+    assert_eq!(graph.children(gene).next().unwrap().id(), Some("ENST1"));
+    assert_eq!(
+        gff.entries[2].to_string(),
+        "1\thavana\texon\t11869\t12227\t.\t+\t.\tParent=ENST1;gene_id=ENSG1;transcript_id=ENST1;exon_number=1"
+    );
+
+    // A shared exon is written under every transcript
+    const GFF3: &str = "ctg1\t.\tgene\t1\t90\t.\t+\t.\tID=gene1
+ctg1\t.\tmRNA\t1\t90\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg1\t.\tmRNA\t1\t50\t.\t+\t.\tID=mRNA2;Parent=gene1
+ctg1\t.\texon\t1\t20\t.\t+\t.\tParent=mRNA1,mRNA2
+";
+    let gff = GFF3.parse::<GFF>().unwrap();
+    let mut written = Vec::new();
+    gff.write_gtf(&mut written).unwrap();
+    let written = String::from_utf8(written).unwrap();
+    let exons = written
+        .lines()
+        .filter(|line| line.contains("\texon\t"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exons,
+        [
+            "ctg1\t.\texon\t1\t20\t.\t+\t.\tgene_id \"gene1\"; transcript_id \"mRNA1\";",
+            "ctg1\t.\texon\t1\t20\t.\t+\t.\tgene_id \"gene1\"; transcript_id \"mRNA2\";"
+        ]
+    );
+
+    let quoted = "ctg1\t.\tgene\t1\t90\t.\t+\t.\tID=gene1;note=a \"b\""
+        .parse::<GFF>()
+        .unwrap();
+    let mut written = Vec::new();
+    assert!(matches!(
+        quoted.write_gtf(&mut written),
+        Err(GffError::InvalidGtfValue { .. })
+    ));
+    assert!(written.is_empty());
+
+    // Semicolons inside quoted values do not end the attribute
+    const NOTE: &str = "1\thavana\tgene\t11869\t14409\t.\t+\t.\tgene_id \"ENSG1\"; note \"a; b\";";
+    let gff = GFF::read_gtf(&mut NOTE.as_bytes()).unwrap();
+    assert_eq!(
+        gff.entries[0].attrs.get("note").unwrap()[0].as_ref(),
+        "a; b"
+    );
+    assert!(gff.entries[0]
+        .to_string()
+        .ends_with("ID=ENSG1;gene_id=ENSG1;note=a%3B b"));
+    assert!(GFF::read_gtf(&mut "1\t.\tgene\t1\t9\t.\t+\t.\tnote \"a;".as_bytes()).is_err());
+}
+
+fn index_gff(ranges: &[(usize, usize)]) -> super::GFF {
+    super::GFF {
+        entries: ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, end))| {
+                format!("ctg1\t.\tgene\t{start}\t{end}\t.\t+\t.\tID=g{i}")
+                    .parse()
+                    .unwrap()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+proptest! {
+    #[test]
+    fn index_matches_scan(
+        ranges in prop::collection::vec((1..500usize, 0..100usize), 0..200),
+        query in (1..600usize, 0..100usize),
+    ) {
+        let ranges = ranges.into_iter().map(|(start, len)| (start, start + len)).collect::<Vec<_>>();
+        let gff = index_gff(&ranges);
+        let index = super::index::IntervalIndex::new(&gff);
+        let (start, end) = (query.0, query.0 + query.1);
+
+        let mut found = index
+            .overlapping("ctg1", start..=end)
+            .map(|entry| entry.attrs.id.clone())
+            .collect::<Vec<_>>();
+        let mut expected = gff
+            .entries
+            .iter()
+            .filter(|entry| entry.range.start <= end && start <= entry.range.end)
+            .map(|entry| entry.attrs.id.clone())
+            .collect::<Vec<_>>();
+        found.sort_by_key(|id| id.as_ref().map(ToString::to_string));
+        expected.sort_by_key(|id| id.as_ref().map(ToString::to_string));
+        prop_assert_eq!(found, expected);
+
+        let preceding = gff.entries.iter().filter(|entry| entry.range.end < start).map(|entry| entry.range.end).max();
+        prop_assert_eq!(index.preceding("ctg1", start).map(|entry| entry.range.end), preceding);
+        let following = gff.entries.iter().filter(|entry| entry.range.start > start).map(|entry| entry.range.start).min();
+        prop_assert_eq!(index.following("ctg1", start).map(|entry| entry.range.start), following);
+    }
+}
+
+#[test]
+fn index_queries() {
+    use super::{index::IntervalIndex, Strand};
+
+    let gff = index_gff(&[(10, 20), (15, 40), (50, 60), (100, 200)]);
+    let index = IntervalIndex::new(&gff);
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.containing("ctg1", 18).count(), 2);
+    assert_eq!(index.containing("ctg2", 18).count(), 0);
+    let range = |entry: Option<&super::Entry>| entry.map(|entry| RangeInclusive::from(entry.range));
+    assert_eq!(
+        range(index.upstream("ctg1", 45, &Strand::Positive)),
+        Some(15..=40)
+    );
+    assert_eq!(
+        range(index.upstream("ctg1", 45, &Strand::Negative)),
+        Some(50..=60)
+    );
+    assert_eq!(
+        range(index.downstream("ctg1", 60, &Strand::Positive)),
+        Some(100..=200)
+    );
+    assert_eq!(range(index.downstream("ctg1", 10, &Strand::Negative)), None);
+
+    let long = IntervalIndex::filtered(&gff, |entry| entry.range.end - entry.range.start > 20);
+    assert_eq!(long.len(), 2);
+}
+
+#[test]
+fn sequence_ontology() {
+    use super::ontology::Term;
+
+    let gene = Term::get("SO:0000704").unwrap();
+    assert_eq!(gene.name, "gene");
+    assert_eq!(Term::get("gene").unwrap().accession, "SO:0000704");
+    assert!(Term::get("not_a_term").is_none());
+
+    let transcript = Term::get("transcript").unwrap();
+    let lnc_rna = Term::get("lnc_RNA").unwrap();
+    assert!(Term::get("mRNA").unwrap().is_a(transcript));
+    assert!(lnc_rna.is_a(transcript));
+    assert!(!transcript.is_a(lnc_rna));
+    assert!(!gene.is_a(transcript));
+
+    let exon = Term::get("exon").unwrap();
+    assert!(exon.is_part_of(lnc_rna));
+    assert!(exon.is_part_of(gene));
+    assert!(Term::get("coding_exon").unwrap().is_part_of(transcript));
+    assert!(Term::get("CDS").unwrap().is_part_of(gene));
+    assert!(!Term::get("CDS").unwrap().is_part_of(lnc_rna));
+    assert!(!gene.is_part_of(exon));
+
+    let entry = "ctg1\t.\tSO:0001877\t1\t10\t.\t+\t.\t."
+        .parse::<super::Entry>()
+        .unwrap();
+    assert_eq!(entry.term(), Some(lnc_rna));
+}
+
+#[test]
+fn validation() {
+    use super::validate::Violation;
+
+    const SRC: &str = "##gff-version 3
+##sequence-region ctg1 1 1000
+ctg1\t.\tgene\t1\t900\t.\t+\t.\tID=gene1
+ctg1\t.\tmRNA\t1\t900\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg1\t.\tCDS\t10\t90\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tCDS\t200\t290\t.\t+\t.\tID=cds1;Parent=mRNA1
+ctg1\t.\texon\t10\t90\t.\t+\t0\tParent=mRNA1
+ctg1\t.\texon\t950\t1100\t.\t+\t.\tParent=mRNA2
+ctg1\t.\tgene\t500\t400\t.\t+\t.\tID=mRNA1
+ctg1\t.\tlnc_RNA\t1\t900\t.\t+\t.\tID=lnc1;Parent=gene1
+ctg1\t.\tCDS\t1\t9\t.\t+\t0\tParent=lnc1
+ctg1\test\tcDNA_match\t1\t20\t.\t+\t.\tID=m1;Target=est1 1 18;Gap=M10 D2 M8
+ctg1\test\tcDNA_match\t1\t20\t.\t+\t.\tID=m2;Target=est1 1 21;Gap=M10 D2 M8
+ctg1\test\tprotein_match\t1\t30\t.\t+\t.\tID=p1;Target=prot1 1 10;Gap=M10
+";
+    let gff = SRC.parse::<super::GFF>().unwrap();
+    let diagnostics = gff.validate();
+    let found = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, &diagnostic.violation))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            (Some(6), &Violation::MissingPhase),
+            (
+                Some(7),
+                &Violation::UnexpectedPhase {
+                    feature_type: "exon".into()
+                }
+            ),
+            (
+                Some(8),
+                &Violation::OutsideSequenceRegion {
+                    seq_id: "ctg1".into(),
+                    range: OneBased::new(950, 1100),
+                    region: OneBased::new(1, 1000)
+                }
+            ),
+            (
+                Some(8),
+                &Violation::UnknownParent {
+                    parent: "mRNA2".into()
+                }
+            ),
+            (
+                Some(9),
+                &Violation::StartAfterEnd {
+                    start: 500,
+                    end: 400
+                }
+            ),
+            (
+                Some(9),
+                &Violation::DuplicateId {
+                    id: "mRNA1".into(),
+                    first_line: Some(4)
+                }
+            ),
+            (
+                Some(11),
+                &Violation::InvalidPartOf {
+                    feature_type: "CDS".into(),
+                    parent: "lnc1".into(),
+                    parent_type: "lnc_RNA".into()
+                }
+            ),
+            (
+                Some(13),
+                &Violation::TargetGapMismatch {
+                    length: 20,
+                    target_length: 21,
+                    gap_length: 20,
+                    gap_target_length: 18
+                }
+            ),
+        ]
+    );
+
+    // The diagnostic points at the attribute at fault
+    let unknown = &diagnostics[3];
+    let text = gff.entries[unknown.entry].to_string();
+    let span = miette::Diagnostic::labels(unknown).unwrap().next().unwrap();
+    assert_eq!(
+        &text[span.offset()..span.offset() + span.len()],
+        "Parent=mRNA2"
+    );
+}
+
+#[test]
+fn multi_valued_attributes() {
+    use super::attr::DbXref;
+
+    let attrs = AttributeSet::parse(
+        "ID=gene1;Alias=EDEN,eden%2C1;Note=one,two;Dbxref=GeneID:1234,HGNC:HGNC%3A5;Ontology_term=GO:0046703;tags=basic,CCDS",
+    )
+    .unwrap();
+    let strs = |vals: &[super::UnescapedString]| {
+        vals.iter().map(|val| val.to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(strs(attrs.alias.as_deref().unwrap()), ["EDEN", "eden,1"]);
+    assert_eq!(strs(attrs.note.as_deref().unwrap()), ["one", "two"]);
+    assert_eq!(strs(attrs.get("tags").unwrap()), ["basic", "CCDS"]);
+    assert!(attrs.get("missing").is_none());
+    assert_eq!(
+        attrs.dbx_ref.as_ref().unwrap()[1],
+        DbXref {
+            db: super::UnescapedString::new("HGNC").unwrap(),
+            id: super::UnescapedString::new("HGNC:5").unwrap(),
+        }
+    );
+    assert_eq!(attrs.dbxref("GeneID"), Some("1234"));
+    assert_eq!(attrs.dbxref("HGNC"), Some("HGNC:5"));
+    assert_eq!(attrs.dbxref("Ensembl"), None);
+    assert!(attrs.has_ontology_term("GO:0046703"));
+    assert!(!attrs.has_ontology_term("GO:0000001"));
+
+    assert_eq!(
+        attrs.to_string(),
+        "ID=gene1;Alias=EDEN,eden%2C1;Note=one,two;Dbxref=GeneID:1234,HGNC:HGNC:5;Ontology_term=GO:0046703;tags=basic,CCDS"
+    );
+    assert!(AttributeSet::parse("Dbxref=GeneID").is_err());
+}
+
+#[test]
+fn percent_encoding() {
+    use super::{GffError, UnescapedString};
+
+    let decode = |src| UnescapedString::new(src).map(|s| s.to_string());
+    assert_eq!(decode("caf%C3%A9").unwrap(), "café");
+    assert_eq!(decode("100%25%3B").unwrap(), "100%;");
+    assert_eq!(decode("%2525").unwrap(), "%25");
+    assert!(matches!(
+        decode("ab%4"),
+        Err(GffError::TruncatedEscape { at: 2 })
+    ));
+    assert!(matches!(
+        decode("ab%"),
+        Err(GffError::TruncatedEscape { .. })
+    ));
+    assert!(matches!(
+        decode("%G1"),
+        Err(GffError::InvalidEscape { at: 0, .. })
+    ));
+    assert!(matches!(decode("%C3"), Err(GffError::StringDecodeErr)));
+
+    let escaped = UnescapedString::new("a b;c=d%25é").unwrap().escape();
+    assert_eq!(escaped, "a%20b%3Bc%3Dd%25%C3%A9");
+}
+
+proptest! {
+    #[test]
+    fn percent_encoding_round_trip(s in any::<String>()) {
+        let unescaped = super::UnescapedString::new(&s.replace('%', "%25")).unwrap();
+        prop_assert_eq!(&*unescaped, &*s);
+        let escaped = unescaped.escape();
+        prop_assert_eq!(&*super::UnescapedString::new(&escaped).unwrap(), &*s);
+    }
+}
+
+#[test]
+fn borrowed_entries() {
+    use std::borrow::Cow;
+
+    use super::{borrowed::EntryRef, reader::GffReader, Entry};
+
+    const LINE: &str =
+        "ctg1\tmy%20source\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA1;Parent=gene1,gene2;Note=a%2Cb";
+    let entry = EntryRef::parse(LINE).unwrap();
+    assert!(matches!(entry.seq_id, Cow::Borrowed("ctg1")));
+    assert!(matches!(entry.source, Cow::Owned(ref source) if source == "my source"));
+    assert_eq!(entry.attrs.id(), Some("mRNA1"));
+    assert_eq!(
+        entry.attrs.parents().collect::<Vec<_>>(),
+        ["gene1", "gene2"]
+    );
+    assert_eq!(entry.attrs.raw("Note"), Some("a%2Cb"));
+    assert_eq!(entry.attrs.get("Note").unwrap().as_deref(), Some("a,b"));
+    assert_eq!(entry.attrs.get("Name").unwrap(), None);
+    assert_eq!(
+        entry.try_to_owned().unwrap(),
+        LINE.parse::<Entry>().unwrap()
+    );
+
+    let empty = EntryRef::parse("ctg1\t.\tgene\t1\t2\t.\t.\t.\t.").unwrap();
+    assert_eq!(empty.attrs.iter().count(), 0);
+    assert!(EntryRef::parse("ctg1\t.\tgene\tone\t2\t.\t.\t.\t.").is_err());
+
+    const SRC: &str = "##gff-version 3
+##sequence-region ctg1 1 10000
+ctg1\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene1
+# comment
+ctg1\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg2\t.\tgene\t10\t90\t.\t-\t.\tID=gene2
+##FASTA
+>ctg1
+ACGT
+";
+    let mut reader = GffReader::new(SRC.as_bytes());
+    let mut genes = Vec::new();
+    while let Some((line, entry)) = reader.read_entry_ref().unwrap() {
+        if entry.feature_type == "gene" {
+            genes.push((line, entry.attrs.id().unwrap().to_owned()));
+        }
+    }
+    assert_eq!(genes, [(3, "gene1".into()), (6, "gene2".into())]);
+    assert!(reader.metadata().sequence_regions.is_some());
+}
+
+#[test]
+fn parse_line_numbers() {
+    use super::{
+        reader::{GffItem, GffReader},
+        GffError, GFF,
+    };
+
+    let mut src = String::from("##gff-version 3\n# comment\n\n");
+    for i in 0..500 {
+        src.push_str(&format!(
+            "ctg1\t.\tgene\t{}\t{}\t.\t+\t.\tID=gene{i}\n",
+            i * 10 + 1,
+            i * 10 + 5
+        ));
+        if i % 100 == 0 {
+            src.push_str("###\n");
+        }
+    }
+    src.push_str("##FASTA\n>ctg1\nACGT\n");
+    let gff = src.parse::<GFF>().unwrap();
+    let streamed = GffReader::new(src.as_bytes())
+        .filter_map(|item| match item.unwrap() {
+            (line, GffItem::Entry(entry)) => Some((line, *entry)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(gff.entries.len(), 500);
+    assert_eq!(gff.sequences.len(), 1);
+    assert_eq!(
+        gff.entries
+            .into_iter()
+            .map(|entry| (entry.line.unwrap(), entry))
+            .collect::<Vec<_>>(),
+        streamed
+    );
+
+    // The earliest malformed line is reported, however the work was split
+    let broken = src
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match i + 1 {
+            250 | 400 => "ctg1\t.\tgene\tone\t5\t.\t+\t.\t.",
+            _ => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let err = broken.parse::<GFF>().unwrap_err();
+    assert!(matches!(err, GffError::AtLine { line: 250, .. }), "{err:?}");
+}
+
+#[test]
+fn gene_models() {
+    const SRC: &str = "##gff-version 3
+ctg1\t.\tgene\t1\t1000\t.\t+\t.\tID=gene1
+ctg1\t.\tmRNA\t100\t700\t.\t+\t.\tID=mRNA1;Parent=gene1
+ctg1\t.\texon\t300\t400\t.\t+\t.\tParent=mRNA1
+ctg1\t.\texon\t100\t200\t.\t+\t.\tParent=mRNA1
+ctg1\t.\texon\t600\t700\t.\t+\t.\tParent=mRNA1
+ctg1\t.\tCDS\t150\t200\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tCDS\t300\t400\t.\t+\t0\tID=cds1;Parent=mRNA1
+ctg1\t.\tCDS\t600\t650\t.\t+\t2\tID=cds1;Parent=mRNA1
+ctg1\t.\tlnc_RNA\t100\t300\t.\t+\t.\tID=lnc1;Parent=gene1
+ctg1\t.\texon\t100\t300\t.\t+\t.\tParent=lnc1
+ctg1\t.\tgene\t2000\t2600\t.\t-\t.\tID=gene2
+ctg1\t.\tgene\t2700\t3000\t.\t-\t.\tID=gene2
+ctg1\t.\tmRNA\t2100\t2500\t.\t-\t.\tID=mRNA2;Parent=gene2
+ctg1\t.\texon\t2100\t2200\t.\t-\t.\tParent=mRNA2
+ctg1\t.\texon\t2400\t2500\t.\t-\t.\tParent=mRNA2
+ctg1\t.\tCDS\t2150\t2200\t.\t-\t0\tID=cds2;Parent=mRNA2
+ctg1\t.\tCDS\t2400\t2450\t.\t-\t1\tID=cds2;Parent=mRNA2
+ctg1\t.\tfive_prime_UTR\t2451\t2500\t.\t-\t.\tParent=mRNA2
+ctg1\t.\tmatch\t1\t10\t.\t+\t.\tID=match1
+";
+    let ranges = |intervals: Vec<OneBased>| {
+        intervals
+            .into_iter()
+            .map(RangeInclusive::from)
+            .collect::<Vec<_>>()
+    };
+    let gff = SRC.parse::<super::GFF>().unwrap();
+    let genes = gff.genes().unwrap();
+    assert_eq!(
+        genes.iter().map(|gene| gene.id).collect::<Vec<_>>(),
+        [Some("gene1"), Some("gene2")]
+    );
+
+    let [mrna, lnc] = &genes[0].transcripts[..] else {
+        panic!("gene1 has two transcripts");
+    };
+    assert_eq!(
+        mrna.exons
+            .iter()
+            .map(|exon| RangeInclusive::from(exon.range()))
+            .collect::<Vec<_>>(),
+        [100..=200, 300..=400, 600..=700]
+    );
+    assert_eq!(mrna.length(), 303);
+    assert_eq!(mrna.cds_length(), 203);
+    assert_eq!(ranges(mrna.introns()), [201..=299, 401..=599]);
+    assert_eq!(mrna.start_codon(), Some([150, 151, 152]));
+    assert_eq!(mrna.stop_codon(), Some([648, 649, 650]));
+    assert_eq!(ranges(mrna.five_prime_utrs()), [100..=149]);
+    assert_eq!(ranges(mrna.three_prime_utrs()), [651..=700]);
+
+    assert_eq!(lnc.id, Some("lnc1"));
+    assert!(!lnc.is_coding());
+    assert_eq!(lnc.length(), 201);
+    assert_eq!(lnc.start_codon(), None);
+    assert!(lnc.five_prime_utrs().is_empty());
+
+    assert_eq!(genes[1].entry.range, OneBased::new(2000, 2600));
+    assert_eq!(genes[1].range, OneBased::new(2000, 3000));
+    let mrna = &genes[1].transcripts[0];
+    assert_eq!(mrna.range, OneBased::new(2100, 2500));
+    assert_eq!(
+        mrna.exons
+            .iter()
+            .map(|exon| RangeInclusive::from(exon.range()))
+            .collect::<Vec<_>>(),
+        [2400..=2500, 2100..=2200]
+    );
+    assert_eq!(mrna.cds[0].phase(), 1);
+    assert_eq!(ranges(mrna.introns()), [2201..=2399]);
+    assert_eq!(mrna.start_codon(), Some([2449, 2448, 2447]));
+    assert_eq!(mrna.stop_codon(), Some([2152, 2151, 2150]));
+    assert_eq!(ranges(mrna.five_prime_utrs()), [2451..=2500]);
+    assert_eq!(ranges(mrna.three_prime_utrs()), [2100..=2149]);
+}
+
+#[test]
+fn alignment_projection() {
+    use super::{
+        alignment::{from_cigar, to_cigar, Alignment},
+        attr::{GapKind, TargetAttr},
+        Entry, GffError,
+    };
+
+    let entry =
+        "ctg1\test\tcDNA_match\t100\t118\t.\t+\t.\tID=m1;Target=est1 1 17;Gap=M8 D3 M6 I1 M2"
+            .parse::<Entry>()
+            .unwrap();
+    let alignment = entry.alignment().unwrap();
+    assert_eq!(alignment.to_target(100), Some(1));
+    assert_eq!(alignment.to_target(107), Some(8));
+    assert_eq!(alignment.to_target(108), None);
+    assert_eq!(alignment.to_target(111), Some(9));
+    assert_eq!(alignment.to_target(117), Some(16));
+    assert_eq!(alignment.to_target(99), None);
+    assert_eq!(alignment.to_target(119), None);
+    assert_eq!(alignment.to_reference(9), Some(OneBased::new(111, 111)));
+    assert_eq!(alignment.to_reference(15), None);
+    assert_eq!(alignment.to_reference(18), None);
+
+    let reversed =
+        "ctg1\test\tcDNA_match\t100\t118\t.\t+\t.\tTarget=est1 1 17 -;Gap=M8 D3 M6 I1 M2"
+            .parse::<Entry>()
+            .unwrap();
+    let alignment = reversed.alignment().unwrap();
+    assert_eq!(alignment.to_target(100), Some(17));
+    assert_eq!(alignment.to_reference(17), Some(OneBased::new(100, 100)));
+
+    let protein = "ctg1\tblastx\tprotein_match\t1\t27\t.\t+\t.\tTarget=p1 1 9;Gap=M3 F1 M2 R1 M4"
+        .parse::<Entry>()
+        .unwrap();
+    let alignment = protein.alignment().unwrap();
+    assert_eq!(alignment.to_target(1), Some(1));
+    assert_eq!(alignment.to_target(9), Some(3));
+    assert_eq!(alignment.to_target(10), None);
+    assert_eq!(alignment.to_target(16), Some(5));
+    assert_eq!(alignment.to_target(17), Some(6));
+    assert_eq!(alignment.to_reference(1), Some(OneBased::new(1, 3)));
+    assert_eq!(alignment.to_reference(6), Some(OneBased::new(16, 18)));
+
+    let ungapped = "ctg1\t.\tmatch_part\t10\t19\t.\t+\t.\tTarget=r1 101 110"
+        .parse::<Entry>()
+        .unwrap();
+    assert_eq!(ungapped.alignment().unwrap().to_target(15), Some(106));
+    assert!(entry.attrs.target.is_some() && ungapped.attrs.gap.is_none());
+
+    let gap = entry.attrs.gap.as_deref().unwrap();
+    assert_eq!(to_cigar(gap, entry.target_scale()).unwrap(), "8M3D6M1I2M");
+    assert_eq!(from_cigar("8M3D6M1I2M", 1).unwrap(), gap);
+    assert_eq!(
+        from_cigar("2S5=1X2M3D4N1I2H", 1).unwrap(),
+        [
+            (GapKind::Match, 8),
+            (GapKind::Delete, 7),
+            (GapKind::Insert, 1)
+        ]
+    );
+    assert!(matches!(
+        to_cigar(protein.attrs.gap.as_deref().unwrap(), 3),
+        Err(GffError::FrameshiftInCigar)
+    ));
+    assert!(matches!(from_cigar("8M3", 1), Err(GffError::InvalidCigar)));
+    assert!(matches!(from_cigar("M", 1), Err(GffError::InvalidCigar)));
+
+    // Protein lengths are scaled to the bases of the reference
+    let codons = "ctg1\tblastx\tprotein_match\t1\t36\t.\t+\t.\tTarget=p2 1 12;Gap=M10 D2"
+        .parse::<Entry>()
+        .unwrap();
+    assert_eq!(codons.target_scale(), 3);
+    let gap = codons.attrs.gap.as_deref().unwrap();
+    let cigar = to_cigar(gap, codons.target_scale()).unwrap();
+    assert_eq!(cigar, "30M6D");
+    assert_eq!(from_cigar(&cigar, 3).unwrap(), gap);
+    assert!(matches!(from_cigar("31M", 3), Err(GffError::InvalidCigar)));
+
+    // An alignment can be built for a target that was not read from a file
+    let target = TargetAttr::new("est1", OneBased::new(1, 17), None);
+    assert_eq!(&target, entry.attrs.target.as_ref().unwrap());
+    let gap = entry.attrs.gap.as_deref();
+    let alignment = Alignment::new(entry.range, entry.strand.as_ref(), &target, gap, 1);
+    assert_eq!(alignment.to_target(111), Some(9));
+    assert_eq!(alignment.target().target_id().to_string(), "est1");
+}
+
+#[test]
+fn metadata_uris() {
+    use super::{
+        meta::{GenomeBuild, Uri},
+        GffError, UnescapedString, GFF,
+    };
+
+    const SRC: &str = "##gff-version 3
+##feature-ontology http://song.cvs.sourceforge.net/*checkout*/song/ontology/so.obo?revision=1.263
+##species https://www.ncbi.nlm.nih.gov/Taxonomy/Browser/wwwtax.cgi?mode=Info&id=9606
+##genome-build NCBI GRCh38.p14
+##organism-name Homo sapiens
+##processed
+ctg1\t.\tgene\t1\t10\t.\t+\t.\tID=gene1
+";
+    let gff = SRC.parse::<GFF>().unwrap();
+    let meta = &gff.metadata;
+    let species = meta.species_uri.as_ref().unwrap();
+    assert_eq!(species.scheme(), "https");
+    assert_eq!(species.authority(), Some("www.ncbi.nlm.nih.gov"));
+    assert_eq!(species.path(), "/Taxonomy/Browser/wwwtax.cgi");
+    assert_eq!(species.query(), Some("mode=Info&id=9606"));
+    assert_eq!(meta.taxon_id(), Some(9606));
+    let ontology = meta.feature_ontology_uri.as_ref().unwrap();
+    assert_eq!(ontology.query_param("revision"), Some("1.263"));
+    assert_eq!(ontology.fragment(), None);
+    assert_eq!(
+        meta.genome_build,
+        Some(GenomeBuild {
+            source: UnescapedString::new("NCBI").unwrap(),
+            name: UnescapedString::new("GRCh38.p14").unwrap(),
+        })
+    );
+    assert_eq!(
+        meta.other_directives.as_deref(),
+        Some(&["organism-name Homo sapiens".into(), "processed".into()][..])
+    );
+
+    let written = gff.to_string();
+    assert!(written.contains("\n##organism-name Homo sapiens\n##processed\n"));
+    assert_eq!(written.parse::<GFF>().unwrap(), gff);
+
+    let urn = "urn:lsid:ncbi.nlm.nih.gov:taxonomy:9606#x"
+        .parse::<Uri>()
+        .unwrap();
+    assert_eq!(urn.scheme(), "urn");
+    assert_eq!(urn.authority(), None);
+    assert_eq!(urn.path(), "lsid:ncbi.nlm.nih.gov:taxonomy:9606");
+    assert_eq!(urn.fragment(), Some("x"));
+    for invalid in [
+        "so.obo",
+        "1http://a",
+        "http://a b",
+        "http://a%2",
+        "http://a%zz",
+    ] {
+        assert!(matches!(Uri::new(invalid), Err(GffError::InvalidUri)));
+    }
+    assert!(matches!(
+        "##gff-version 3\n##species not a uri\n".parse::<GFF>(),
+        Err(GffError::AtLine { line: 2, .. })
+    ));
+    let Err(GffError::AtLine { line: 2, source }) = "##gff-version 3\n##species\n".parse::<GFF>()
+    else {
+        panic!("A bare ##species directive must be rejected");
+    };
+    assert!(matches!(
+        *source,
+        GffError::MissingDirectiveValue { ref directive } if &**directive == "species"
+    ));
+}
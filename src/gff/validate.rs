@@ -0,0 +1,249 @@
+use std::{borrow::Borrow, collections::HashMap};
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use super::{attr::GapKind, ontology::Term, Entry, GFF};
+use crate::genomics::interval::OneBased;
+
+/// A way in which an entry breaks the GFF3 specification
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Violation {
+    #[error("Start {start} is after end {end}")]
+    StartAfterEnd { start: usize, end: usize },
+    #[error(
+        "{}..{} lies outside of the sequence-region {}..{} of {seq_id}",
+        .range.start, .range.end, .region.start, .region.end
+    )]
+    OutsideSequenceRegion {
+        seq_id: Box<str>,
+        range: OneBased,
+        region: OneBased,
+    },
+    #[error("CDS features must have a phase")]
+    MissingPhase,
+    #[error("Only CDS features can have a phase, but this {feature_type} has one")]
+    UnexpectedPhase { feature_type: Box<str> },
+    #[error(
+        "ID {id} is already used by an entry{} with a different type or sequence",
+        .first_line.map_or_else(String::new, |line| format!(" on line {line}"))
+    )]
+    DuplicateId {
+        id: Box<str>,
+        /// The line of the first entry with the ID, if it was read from a file
+        first_line: Option<usize>,
+    },
+    #[error("Parent {parent} does not exist")]
+    UnknownParent { parent: Box<str> },
+    #[error("A {feature_type} cannot be part of the {parent_type} {parent}")]
+    InvalidPartOf {
+        feature_type: Box<str>,
+        parent: Box<str>,
+        parent_type: Box<str>,
+    },
+    #[error("Gap aligns {gap_length} bases of the feature and {gap_target_length} of the target, but they span {length} and {target_length}")]
+    TargetGapMismatch {
+        length: usize,
+        target_length: usize,
+        gap_length: usize,
+        gap_target_length: usize,
+    },
+}
+
+/// A [`Violation`] found by [`GFF::validate`], pointing at the part of the entry at fault
+#[derive(Debug, Error, Diagnostic)]
+#[error("{violation}")]
+pub struct ValidationDiagnostic {
+    pub violation: Violation,
+    /// The index of the entry in [`GFF::entries`]
+    pub entry: usize,
+    /// The line of the entry, if it was read from a file, as in [`Entry::line`]
+    pub line: Option<usize>,
+    #[source_code]
+    src: NamedSource,
+    #[label("Here")]
+    span: SourceSpan,
+}
+
+/// The part of an entry a [`Violation`] is about
+#[derive(Clone, Copy)]
+enum Location {
+    /// A column, numbered from 0
+    Column(usize),
+    Attribute(&'static str),
+}
+
+impl ValidationDiagnostic {
+    fn new(gff: &GFF, entry: usize, violation: Violation, location: Location) -> Self {
+        let line = gff.entries[entry].line;
+        let text = gff.entries[entry].to_string();
+        let span = span(&text, location);
+        let name = line.map_or_else(|| format!("entry {entry}"), |line| format!("line {line}"));
+        Self {
+            violation,
+            entry,
+            line,
+            src: NamedSource::new(name, text),
+            span,
+        }
+    }
+}
+
+/// Finds the span of `location` in the text of an entry
+fn span(text: &str, location: Location) -> SourceSpan {
+    let column = match location {
+        Location::Column(column) => column,
+        Location::Attribute(_) => 8,
+    };
+    let start = text
+        .split('\t')
+        .take(column)
+        .map(|column| column.len() + 1)
+        .sum::<usize>();
+    let columns = &text[start..];
+    let len = columns.find('\t').unwrap_or(columns.len());
+    let Location::Attribute(key) = location else {
+        return (start, len).into();
+    };
+    let mut offset = start;
+    for attr in columns.split(';') {
+        if attr.split_once('=').map(|(k, _)| k) == Some(key) {
+            return (offset, attr.len()).into();
+        }
+        offset += attr.len() + 1;
+    }
+    (start, len).into()
+}
+
+/// The number of bases an alignment covers on the feature and on the target
+fn gap_lengths(gap: &[(GapKind, usize)]) -> (usize, usize) {
+    gap.iter()
+        .fold((0, 0), |(feature, target), &(kind, len)| match kind {
+            GapKind::Match => (feature + len, target + len),
+            GapKind::Delete => (feature + len, target),
+            GapKind::Insert => (feature, target + len),
+            GapKind::FwdFrameShift | GapKind::RevFrameShift => (feature, target),
+        })
+}
+
+fn is_cds(entry: &Entry) -> bool {
+    let cds = Term::get("CDS").expect("CDS is an embedded term");
+    entry.term().is_some_and(|term| term.is_a(cds))
+}
+
+impl GFF {
+    /// Checks the entries against the GFF3 specification, returning every problem found in order of entry.
+    ///
+    /// Entries are checked for:
+    /// - a start after their end, or a range outside the `##sequence-region` of their sequence
+    /// - a CDS without a phase, or a phase on anything but a CDS
+    /// - an `ID` shared with an entry of a different type or sequence, since only the parts of one
+    ///   discontinuous feature may share an `ID`
+    /// - a `Parent` that no entry has as its `ID`, or whose type the entry's type cannot be part of
+    ///   according to the [Sequence Ontology](Term)
+    /// - a `Gap` that does not span the lengths of the entry and its `Target`, allowing for protein
+    ///   targets of a third of the length; alignments with frameshifts are not checked
+    #[tracing::instrument(skip_all)]
+    #[must_use]
+    pub fn validate(&self) -> Vec<ValidationDiagnostic> {
+        let mut by_id: HashMap<&str, usize> = HashMap::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            if let Some(ref id) = entry.attrs.id {
+                by_id.entry(id.borrow()).or_insert(i);
+            }
+        }
+        let regions = self.metadata.sequence_regions.as_ref();
+
+        let mut diagnostics = Vec::new();
+        for (i, entry) in self.entries.iter().enumerate() {
+            let mut report = |violation, location| {
+                diagnostics.push(ValidationDiagnostic::new(self, i, violation, location));
+            };
+            let (start, end) = (entry.range.start, entry.range.end);
+            if start > end {
+                report(Violation::StartAfterEnd { start, end }, Location::Column(3));
+            }
+            if let Some(region) = regions.and_then(|regions| regions.get(&entry.seq_id)) {
+                if start < region.start || end > region.end {
+                    let violation = Violation::OutsideSequenceRegion {
+                        seq_id: (*entry.seq_id).into(),
+                        range: entry.range,
+                        region: *region,
+                    };
+                    report(violation, Location::Column(3));
+                }
+            }
+
+            match (is_cds(entry), entry.phase) {
+                (true, None) => report(Violation::MissingPhase, Location::Column(7)),
+                (false, Some(_)) => {
+                    let violation = Violation::UnexpectedPhase {
+                        feature_type: (*entry.feature_type).into(),
+                    };
+                    report(violation, Location::Column(7));
+                }
+                _ => {}
+            }
+
+            if let Some(ref id) = entry.attrs.id {
+                let id: &str = id.borrow();
+                let first = by_id[id];
+                let other = &self.entries[first];
+                if first != i
+                    && (other.seq_id != entry.seq_id || other.feature_type != entry.feature_type)
+                {
+                    let violation = Violation::DuplicateId {
+                        id: id.into(),
+                        first_line: other.line,
+                    };
+                    report(violation, Location::Attribute("ID"));
+                }
+            }
+
+            for parent in entry.attrs.parent.iter().flatten() {
+                let parent: &str = parent.borrow();
+                let Some(&parent_index) = by_id.get(parent) else {
+                    report(
+                        Violation::UnknownParent {
+                            parent: parent.into(),
+                        },
+                        Location::Attribute("Parent"),
+                    );
+                    continue;
+                };
+                let parent_entry = &self.entries[parent_index];
+                if let (Some(term), Some(parent_term)) = (entry.term(), parent_entry.term()) {
+                    if !term.is_part_of(parent_term) {
+                        let violation = Violation::InvalidPartOf {
+                            feature_type: (*entry.feature_type).into(),
+                            parent: parent.into(),
+                            parent_type: (*parent_entry.feature_type).into(),
+                        };
+                        report(violation, Location::Attribute("Parent"));
+                    }
+                }
+            }
+
+            if let (Some(target), Some(gap)) = (&entry.attrs.target, &entry.attrs.gap) {
+                let has_frameshift = gap.iter().any(|(kind, _)| {
+                    matches!(kind, GapKind::FwdFrameShift | GapKind::RevFrameShift)
+                });
+                let length = entry.range.len();
+                let target_length = target.range().len();
+                let (gap_length, gap_target_length) = gap_lengths(gap);
+                let matches =
+                    |scale| gap_length * scale == length && gap_target_length == target_length;
+                if !has_frameshift && !matches(1) && !matches(3) {
+                    let violation = Violation::TargetGapMismatch {
+                        length,
+                        target_length,
+                        gap_length,
+                        gap_target_length,
+                    };
+                    report(violation, Location::Attribute("Gap"));
+                }
+            }
+        }
+        diagnostics
+    }
+}
@@ -11,4 +11,19 @@ pub mod proteomics;
 pub(crate) type NomResult<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
 
 #[cfg(test)]
-mod tests {}
+pub(crate) mod tests {
+    use crate::{
+        fastq::{FastQRecord, Phred},
+        genomics::genome::DnaSeq,
+    };
+
+    /// Parses a DNA sequence, panicking on invalid bases
+    pub(crate) fn seq(src: &str) -> DnaSeq {
+        src.chars().map(|c| c.try_into().unwrap()).collect()
+    }
+
+    /// Parses a read named `read` with the given bases and Phred+33 quality line
+    pub(crate) fn read(seq: &str, qual: &str) -> FastQRecord<DnaSeq, Phred> {
+        FastQRecord::parse(&format!("@read\n{seq}\n+\n{qual}\n")).unwrap()
+    }
+}
@@ -41,6 +41,10 @@ impl Sequence for Proteome {
         todo!()
     }
 
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
     const VALID_CHARS: &'static str = "ARNDCQEGHILKMFPSTWYVUO*";
 }
 